@@ -1,44 +1,136 @@
-use flatbuffers::{FlatBufferBuilder, WIPOffset};
+use flatbuffers::{FlatBufferBuilder, ForwardsUOffset, TableFinishedWIPOffset, Vector, WIPOffset};
+use indexmap::IndexMap;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
+#[path = "src/properties.rs"]
+mod properties;
+
+use properties::parse_properties;
+
 #[derive(Deserialize, Debug)]
 struct TomlConfig {
     #[serde(rename = "static", default)]
-    static_styles: HashMap<String, String>,
+    static_styles: IndexMap<String, String>,
+    #[serde(default)]
+    dynamic: IndexMap<String, IndexMap<String, DynamicValueConfig>>,
+    #[serde(default)]
+    generators: IndexMap<String, GeneratorConfig>,
+    #[serde(default)]
+    screens: IndexMap<String, String>,
+    #[serde(default)]
+    media: IndexMap<String, String>,
+    #[serde(default)]
+    states: IndexMap<String, String>,
+    #[serde(default)]
+    functional_variants: IndexMap<String, String>,
+    #[serde(default)]
+    container_queries: IndexMap<String, String>,
+    #[serde(default)]
+    keyframes: IndexMap<String, String>,
+    #[serde(default)]
+    variables: IndexMap<String, String>,
+    #[serde(default)]
+    composites: IndexMap<String, String>,
+    #[serde(default)]
+    selectors: IndexMap<String, String>,
+    #[serde(default)]
+    theme: ThemeConfig,
+    #[serde(default)]
+    color_utilities: IndexMap<String, String>,
+    #[serde(default)]
+    palette_file: Option<String>,
+    #[serde(default)]
+    palette: IndexMap<String, String>,
     #[serde(default)]
-    dynamic: HashMap<String, HashMap<String, String>>,
+    opacity_strategy: Option<String>,
     #[serde(default)]
-    generators: HashMap<String, GeneratorConfig>,
+    dark_mode: Option<String>,
     #[serde(default)]
-    screens: HashMap<String, String>,
+    important: Option<ImportantConfig>,
     #[serde(default)]
-    states: HashMap<String, String>,
+    prefix: Option<String>,
     #[serde(default)]
-    container_queries: HashMap<String, String>,
+    separator: Option<String>,
+    #[serde(default)]
+    layers: Option<LayersConfig>,
+    #[serde(default)]
+    targets: IndexMap<String, String>,
+    #[serde(default)]
+    preflight: bool,
+    #[serde(default)]
+    preflight_disable: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LayersConfig {
+    #[serde(default)]
+    order: Vec<String>,
+    theme: Option<String>,
+    base: Option<String>,
+    utilities: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ThemeConfig {
+    #[serde(default)]
+    fonts: IndexMap<String, String>,
+    #[serde(default)]
+    colors: IndexMap<String, String>,
+    #[serde(default)]
+    dark: IndexMap<String, String>,
+    #[serde(flatten)]
+    tokens: IndexMap<String, String>,
+}
+
+/// `important = true` marks every declaration, `important = "#app"` scopes every rule.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum ImportantConfig {
+    Flag(bool),
+    Selector(String),
+}
+
+/// A `[dynamic]` value is either one value for the key's properties or a table of
+/// declarations emitted as they are, in the order they are written.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum DynamicValueConfig {
+    Value(String),
+    Declarations(IndexMap<String, String>),
 }
 
 #[derive(Deserialize, Debug, Clone)]
 struct GeneratorConfig {
     multiplier: f32,
     unit: String,
+    #[serde(default)]
+    fractions: bool,
+    #[serde(default)]
+    keywords: IndexMap<String, String>,
+    #[serde(default)]
+    min_viewport: Option<f32>,
+    #[serde(default)]
+    max_viewport: Option<f32>,
 }
 
 fn main() {
     let fbs_files = ["styles.fbs"];
     let toml_path = "styles.toml";
+    let preflight_path = "src/engine/preflight.css";
     let out_dir = std::env::var("OUT_DIR").unwrap();
 
     for fbs_file in fbs_files.iter() {
         println!("cargo:rerun-if-changed={}", fbs_file);
     }
     println!("cargo:rerun-if-changed={}", toml_path);
+    println!("cargo:rerun-if-changed={}", preflight_path);
 
     flatc_rust::run(flatc_rust::Args {
         lang: "rust",
-        inputs: &fbs_files.iter().map(|s| Path::new(s)).collect::<Vec<_>>(),
+        inputs: &fbs_files.iter().map(Path::new).collect::<Vec<_>>(),
         out_dir: Path::new(&out_dir),
         includes: &[Path::new("src")],
         ..Default::default()
@@ -61,26 +153,82 @@ fn main() {
         style_offsets.push(style_offset);
     }
 
+    let mut palette = match &toml_data.palette_file {
+        Some(palette_file) => {
+            println!("cargo:rerun-if-changed={}", palette_file);
+            load_palette(palette_file)
+        }
+        None => IndexMap::new(),
+    };
+    palette.extend(toml_data.palette.clone());
+
+    // Palette colors expand across every color utility, and theme colors and fonts
+    // become utilities reading their custom properties.
+    let mut dynamics = toml_data.dynamic;
+    for (prefix, properties) in &toml_data.color_utilities {
+        let values = dynamics
+            .entry(format!("{}|{}", prefix, properties))
+            .or_default();
+        for (name, color) in &palette {
+            values.insert(name.clone(), DynamicValueConfig::Value(color.clone()));
+        }
+        for name in toml_data
+            .theme
+            .colors
+            .keys()
+            .chain(toml_data.theme.dark.keys())
+        {
+            values.insert(
+                name.clone(),
+                DynamicValueConfig::Value(format!("var(--color-{})", name)),
+            );
+        }
+    }
+    if !toml_data.theme.fonts.is_empty() {
+        let values = dynamics.entry("font|font-family".to_string()).or_default();
+        for name in toml_data.theme.fonts.keys() {
+            values.insert(
+                name.clone(),
+                DynamicValueConfig::Value(format!("var(--font-{})", name)),
+            );
+        }
+    }
+
     let mut dynamic_offsets = Vec::new();
-    for (key, values) in toml_data.dynamic {
+    for (key, values) in dynamics {
         let parts: Vec<&str> = key.split('|').collect();
         if parts.len() != 2 {
             println!("cargo:warning=Invalid dynamic key format in styles.toml: '{}'. Skipping.", key);
             continue;
         }
         let key_name = parts[0];
-        let property = parts[1];
+        let properties = parse_properties(parts[1]);
 
         let key_offset = builder.create_string(key_name);
-        let property_offset = builder.create_string(property);
+        let properties_vec = create_properties(&mut builder, &properties);
 
         let mut value_offsets = Vec::new();
         for (suffix, value) in values {
             let suffix_offset = builder.create_string(&suffix);
+            let (value, declarations) = match value {
+                DynamicValueConfig::Value(value) => (value, IndexMap::new()),
+                DynamicValueConfig::Declarations(declarations) => (String::new(), declarations),
+            };
             let value_offset = builder.create_string(&value);
+            let mut declaration_offsets = Vec::new();
+            for (property, value) in &declarations {
+                let property_offset = builder.create_string(property);
+                let value_offset = builder.create_string(value.trim_end().trim_end_matches(';'));
+                let table_wip = builder.start_table();
+                builder.push_slot(4, property_offset, WIPOffset::new(0));
+                builder.push_slot(6, value_offset, WIPOffset::new(0));
+                declaration_offsets.push(builder.end_table(table_wip));
+            }
+            let declarations_vec = builder.create_vector(&declaration_offsets);
             let table_wip = builder.start_table();
             builder.push_slot(4, suffix_offset, WIPOffset::new(0));
             builder.push_slot(6, value_offset, WIPOffset::new(0));
+            builder.push_slot(8, declarations_vec, WIPOffset::new(0));
             let value_offset = builder.end_table(table_wip);
             value_offsets.push(value_offset);
         }
@@ -88,7 +236,7 @@ fn main() {
 
         let table_wip = builder.start_table();
         builder.push_slot(4, key_offset, WIPOffset::new(0));
-        builder.push_slot(6, property_offset, WIPOffset::new(0));
+        builder.push_slot(6, properties_vec, WIPOffset::new(0));
         builder.push_slot(8, values_vec, WIPOffset::new(0));
         let dynamic_offset = builder.end_table(table_wip);
         dynamic_offsets.push(dynamic_offset);
@@ -102,17 +250,53 @@ fn main() {
             continue;
         }
         let prefix = parts[0];
-        let property = parts[1];
+        let properties = parse_properties(parts[1]);
+
+        match (config.min_viewport, config.max_viewport) {
+            (Some(min_viewport), Some(max_viewport)) if min_viewport >= max_viewport => {
+                println!(
+                    "cargo:warning=Generator '{}' needs min_viewport ({}) below max_viewport ({}) to be fluid. Skipping.",
+                    key, min_viewport, max_viewport
+                );
+                continue;
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                println!(
+                    "cargo:warning=Generator '{}' needs both min_viewport and max_viewport to be fluid. Skipping.",
+                    key
+                );
+                continue;
+            }
+            _ => {}
+        }
 
         let prefix_offset = builder.create_string(prefix);
-        let property_offset = builder.create_string(property);
+        let properties_vec = create_properties(&mut builder, &properties);
         let unit_offset = builder.create_string(&config.unit);
 
+        let mut keyword_offsets = Vec::new();
+        for (name, value) in &config.keywords {
+            let name_offset = builder.create_string(name);
+            let value_offset = builder.create_string(value);
+            let table_wip = builder.start_table();
+            builder.push_slot(4, name_offset, WIPOffset::new(0));
+            builder.push_slot(6, value_offset, WIPOffset::new(0));
+            keyword_offsets.push(builder.end_table(table_wip));
+        }
+        let keywords_vec = builder.create_vector(&keyword_offsets);
+
         let table_wip = builder.start_table();
         builder.push_slot(4, prefix_offset, WIPOffset::new(0));
-        builder.push_slot(6, property_offset, WIPOffset::new(0));
+        builder.push_slot(6, properties_vec, WIPOffset::new(0));
         builder.push_slot(8, config.multiplier, 0.0f32);
         builder.push_slot(10, unit_offset, WIPOffset::new(0));
+        builder.push_slot(12, config.fractions, false);
+        builder.push_slot(14, keywords_vec, WIPOffset::new(0));
+        if let (Some(min_viewport), Some(max_viewport)) = (config.min_viewport, config.max_viewport)
+        {
+            builder.push_slot(16, min_viewport, 0.0f32);
+            builder.push_slot(18, max_viewport, 0.0f32);
+        }
         let gen_offset = builder.end_table(table_wip);
         generator_offsets.push(gen_offset);
     }
@@ -128,6 +312,17 @@ fn main() {
         screen_offsets.push(screen_offset);
     }
 
+    let mut media_offsets = Vec::new();
+    for (name, value) in toml_data.media {
+        let name_offset = builder.create_string(&name);
+        let value_offset = builder.create_string(value.trim());
+        let table_wip = builder.start_table();
+        builder.push_slot(4, name_offset, WIPOffset::new(0));
+        builder.push_slot(6, value_offset, WIPOffset::new(0));
+        let media_offset = builder.end_table(table_wip);
+        media_offsets.push(media_offset);
+    }
+
     let mut state_offsets = Vec::new();
     for (name, value) in toml_data.states {
         let name_offset = builder.create_string(&name);
@@ -139,6 +334,24 @@ fn main() {
         state_offsets.push(state_offset);
     }
 
+    let mut functional_offsets = Vec::new();
+    for (name, template) in toml_data.functional_variants {
+        if !template.contains("{value}") {
+            println!(
+                "cargo:warning=Functional variant '{}' has no {{value}} in its template. Skipping.",
+                name
+            );
+            continue;
+        }
+        let name_offset = builder.create_string(&name);
+        let template_offset = builder.create_string(&template);
+        let table_wip = builder.start_table();
+        builder.push_slot(4, name_offset, WIPOffset::new(0));
+        builder.push_slot(6, template_offset, WIPOffset::new(0));
+        let functional_offset = builder.end_table(table_wip);
+        functional_offsets.push(functional_offset);
+    }
+
     let mut cq_offsets = Vec::new();
     for (name, value) in toml_data.container_queries {
         let name_offset = builder.create_string(&name);
@@ -150,12 +363,223 @@ fn main() {
         cq_offsets.push(cq_offset);
     }
 
+    let mut keyframe_offsets = Vec::new();
+    for (name, css) in toml_data.keyframes {
+        let name_offset = builder.create_string(&name);
+        let css_offset = builder.create_string(css.trim());
+        let table_wip = builder.start_table();
+        builder.push_slot(4, name_offset, WIPOffset::new(0));
+        builder.push_slot(6, css_offset, WIPOffset::new(0));
+        let keyframe_offset = builder.end_table(table_wip);
+        keyframe_offsets.push(keyframe_offset);
+    }
+
+    let mut variable_offsets = Vec::new();
+    for (name, value) in toml_data.variables {
+        if !name.starts_with("--") {
+            println!(
+                "cargo:warning=Variable '{}' in styles.toml is not a custom property. Skipping.",
+                name
+            );
+            continue;
+        }
+        let name_offset = builder.create_string(&name);
+        let value_offset = builder.create_string(&value);
+        let table_wip = builder.start_table();
+        builder.push_slot(4, name_offset, WIPOffset::new(0));
+        builder.push_slot(6, value_offset, WIPOffset::new(0));
+        let variable_offset = builder.end_table(table_wip);
+        variable_offsets.push(variable_offset);
+    }
+
+    let mut composite_offsets = Vec::new();
+    for (property, value) in toml_data.composites {
+        let property_offset = builder.create_string(&property);
+        let value_offset = builder.create_string(&value);
+        let table_wip = builder.start_table();
+        builder.push_slot(4, property_offset, WIPOffset::new(0));
+        builder.push_slot(6, value_offset, WIPOffset::new(0));
+        let composite_offset = builder.end_table(table_wip);
+        composite_offsets.push(composite_offset);
+    }
+
+    let mut selector_offsets = Vec::new();
+    for (prefix, suffix) in toml_data.selectors {
+        let prefix_offset = builder.create_string(&prefix);
+        let suffix_offset = builder.create_string(suffix.trim());
+        let table_wip = builder.start_table();
+        builder.push_slot(4, prefix_offset, WIPOffset::new(0));
+        builder.push_slot(6, suffix_offset, WIPOffset::new(0));
+        let selector_offset = builder.end_table(table_wip);
+        selector_offsets.push(selector_offset);
+    }
+
+    let mut theme_tokens: BTreeMap<String, (Option<String>, Option<String>)> = BTreeMap::new();
+    let theme = toml_data.theme;
+    for (name, value) in theme.tokens {
+        theme_tokens.entry(format!("--{}", name)).or_default().0 = Some(value);
+    }
+    for (name, value) in theme.fonts {
+        theme_tokens
+            .entry(format!("--font-{}", name))
+            .or_default()
+            .0 = Some(value);
+    }
+    for (name, value) in theme.colors {
+        theme_tokens
+            .entry(format!("--color-{}", name))
+            .or_default()
+            .0 = Some(value);
+    }
+    for (name, value) in theme.dark {
+        theme_tokens
+            .entry(format!("--color-{}", name))
+            .or_default()
+            .1 = Some(value);
+    }
+
+    let mut theme_offsets = Vec::new();
+    for (name, (value, dark)) in theme_tokens {
+        let name_offset = builder.create_string(&name);
+        let value_offset = value.map(|value| builder.create_string(&value));
+        let dark_offset = dark.map(|dark| builder.create_string(&dark));
+        let table_wip = builder.start_table();
+        builder.push_slot(4, name_offset, WIPOffset::new(0));
+        if let Some(offset) = value_offset {
+            builder.push_slot(6, offset, WIPOffset::new(0));
+        }
+        if let Some(offset) = dark_offset {
+            builder.push_slot(8, offset, WIPOffset::new(0));
+        }
+        let theme_offset = builder.end_table(table_wip);
+        theme_offsets.push(theme_offset);
+    }
+
     let styles_vec = builder.create_vector(&style_offsets);
     let dynamic_vec = builder.create_vector(&dynamic_offsets);
     let generators_vec = builder.create_vector(&generator_offsets);
     let screens_vec = builder.create_vector(&screen_offsets);
+    let media_vec = builder.create_vector(&media_offsets);
+    let functional_vec = builder.create_vector(&functional_offsets);
     let states_vec = builder.create_vector(&state_offsets);
     let cq_vec = builder.create_vector(&cq_offsets);
+    let keyframes_vec = builder.create_vector(&keyframe_offsets);
+    let variables_vec = builder.create_vector(&variable_offsets);
+    let composites_vec = builder.create_vector(&composite_offsets);
+    let selectors_vec = builder.create_vector(&selector_offsets);
+    let theme_vec = builder.create_vector(&theme_offsets);
+
+    let opacity_strategy_offset = toml_data.opacity_strategy.as_deref().map(|strategy| {
+        if !matches!(strategy, "color-mix" | "rewrite") {
+            println!(
+                "cargo:warning=Unknown opacity_strategy '{}' in styles.toml, using 'color-mix'.",
+                strategy
+            );
+        }
+        builder.create_string(strategy)
+    });
+    let dark_mode_offset = toml_data
+        .dark_mode
+        .as_deref()
+        .map(|dark_mode| builder.create_string(dark_mode));
+    let important_selector_offset = match &toml_data.important {
+        Some(ImportantConfig::Selector(selector)) => Some(builder.create_string(selector)),
+        _ => None,
+    };
+    let prefix_offset = toml_data
+        .prefix
+        .as_deref()
+        .map(|prefix| builder.create_string(prefix));
+    let separator_offset = toml_data.separator.as_deref().and_then(|separator| {
+        if separator.is_empty() || separator.contains(['[', ']', '(', ')', '!']) {
+            println!(
+                "cargo:warning=Invalid separator '{}' in styles.toml, using ':'.",
+                separator
+            );
+            return None;
+        }
+        Some(builder.create_string(separator))
+    });
+
+    let layers_offset = toml_data.layers.map(|layers| {
+        let theme = layers.theme.unwrap_or_else(|| "theme".to_string());
+        let base = layers.base.unwrap_or_else(|| "base".to_string());
+        let utilities = layers.utilities.unwrap_or_else(|| "utilities".to_string());
+        let mut order = layers.order;
+        for name in [&theme, &base, &utilities] {
+            if !order.contains(name) {
+                println!(
+                    "cargo:warning=Layer '{}' is missing from [layers] order, adding it last.",
+                    name
+                );
+                order.push(name.clone());
+            }
+        }
+        let order_offsets: Vec<_> = order
+            .iter()
+            .map(|name| builder.create_string(name))
+            .collect();
+        let order_vec = builder.create_vector(&order_offsets);
+        let theme_offset = builder.create_string(&theme);
+        let base_offset = builder.create_string(&base);
+        let utilities_offset = builder.create_string(&utilities);
+        let table_wip = builder.start_table();
+        builder.push_slot(4, order_vec, WIPOffset::new(0));
+        builder.push_slot(6, theme_offset, WIPOffset::new(0));
+        builder.push_slot(8, base_offset, WIPOffset::new(0));
+        builder.push_slot(10, utilities_offset, WIPOffset::new(0));
+        builder.end_table(table_wip)
+    });
+    let preflight_css = fs::read_to_string(preflight_path).unwrap_or_default();
+    let preflight_rules: Vec<&str> = preflight_css
+        .split("/* @rule ")
+        .skip(1)
+        .filter_map(|section| Some(section.split_once("*/")?.0.trim()))
+        .collect();
+    for name in &toml_data.preflight_disable {
+        if !preflight_rules.contains(&name.as_str()) {
+            println!(
+                "cargo:warning=Unknown preflight rule '{}' in preflight_disable.",
+                name
+            );
+        }
+    }
+    let preflight_disable_offsets: Vec<_> = toml_data
+        .preflight_disable
+        .iter()
+        .map(|name| builder.create_string(name))
+        .collect();
+    let preflight_disable_vec = builder.create_vector(&preflight_disable_offsets);
+
+    let mut target_offsets = Vec::new();
+    for (browser, version) in &toml_data.targets {
+        let known = matches!(
+            browser.as_str(),
+            "android"
+                | "chrome"
+                | "edge"
+                | "firefox"
+                | "ie"
+                | "ios_saf"
+                | "opera"
+                | "safari"
+                | "samsung"
+        );
+        if !known {
+            println!(
+                "cargo:warning=Unknown browser '{}' in [targets]. Skipping.",
+                browser
+            );
+            continue;
+        }
+        let browser_offset = builder.create_string(browser);
+        let version_offset = builder.create_string(version);
+        let table_wip = builder.start_table();
+        builder.push_slot(4, browser_offset, WIPOffset::new(0));
+        builder.push_slot(6, version_offset, WIPOffset::new(0));
+        target_offsets.push(builder.end_table(table_wip));
+    }
+    let targets_vec = builder.create_vector(&target_offsets);
 
     let table_wip = builder.start_table();
     builder.push_slot(4, styles_vec, WIPOffset::new(0));
@@ -164,6 +588,39 @@ fn main() {
     builder.push_slot(10, screens_vec, WIPOffset::new(0));
     builder.push_slot(12, states_vec, WIPOffset::new(0));
     builder.push_slot(14, cq_vec, WIPOffset::new(0));
+    if let Some(offset) = opacity_strategy_offset {
+        builder.push_slot(16, offset, WIPOffset::new(0));
+    }
+    if let Some(offset) = dark_mode_offset {
+        builder.push_slot(18, offset, WIPOffset::new(0));
+    }
+    if matches!(toml_data.important, Some(ImportantConfig::Flag(true))) {
+        builder.push_slot(20, true, false);
+    }
+    if let Some(offset) = important_selector_offset {
+        builder.push_slot(22, offset, WIPOffset::new(0));
+    }
+    builder.push_slot(24, keyframes_vec, WIPOffset::new(0));
+    builder.push_slot(26, variables_vec, WIPOffset::new(0));
+    builder.push_slot(28, composites_vec, WIPOffset::new(0));
+    builder.push_slot(30, selectors_vec, WIPOffset::new(0));
+    builder.push_slot(32, theme_vec, WIPOffset::new(0));
+    if let Some(offset) = prefix_offset {
+        builder.push_slot(34, offset, WIPOffset::new(0));
+    }
+    if let Some(offset) = separator_offset {
+        builder.push_slot(36, offset, WIPOffset::new(0));
+    }
+    builder.push_slot(38, media_vec, WIPOffset::new(0));
+    builder.push_slot(40, functional_vec, WIPOffset::new(0));
+    if let Some(offset) = layers_offset {
+        builder.push_slot(42, offset, WIPOffset::new(0));
+    }
+    builder.push_slot(44, targets_vec, WIPOffset::new(0));
+    if toml_data.preflight {
+        builder.push_slot(46, true, false);
+    }
+    builder.push_slot(48, preflight_disable_vec, WIPOffset::new(0));
     let config_root = builder.end_table(table_wip);
 
     builder.finish(config_root, None);
@@ -173,3 +630,47 @@ fn main() {
     fs::create_dir_all(styles_bin_path.parent().unwrap()).expect("Failed to create .dx directory");
     fs::write(styles_bin_path, buf).expect("Failed to write styles.bin");
 }
+
+/// Reads a palette file of `name = "color"` entries. Names may carry a `bg-` prefix,
+/// as in `bg-red-500`, which is dropped.
+fn load_palette(path: &str) -> IndexMap<String, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            println!("cargo:warning=Failed to read palette '{}': {}", path, err);
+            return IndexMap::new();
+        }
+    };
+    let colors: IndexMap<String, String> = match toml::from_str(&content) {
+        Ok(colors) => colors,
+        Err(err) => {
+            println!("cargo:warning=Failed to parse palette '{}': {}", path, err);
+            return IndexMap::new();
+        }
+    };
+    colors
+        .into_iter()
+        .map(|(name, color)| match name.strip_prefix("bg-") {
+            Some(name) => (name.to_string(), color),
+            None => (name, color),
+        })
+        .collect()
+}
+
+fn create_properties<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    properties: &[(String, Option<String>)],
+) -> WIPOffset<Vector<'a, ForwardsUOffset<TableFinishedWIPOffset>>> {
+    let mut property_offsets = Vec::new();
+    for (name, template) in properties {
+        let name_offset = builder.create_string(name);
+        let template_offset = template.as_ref().map(|t| builder.create_string(t));
+        let table_wip = builder.start_table();
+        builder.push_slot(4, name_offset, WIPOffset::new(0));
+        if let Some(template_offset) = template_offset {
+            builder.push_slot(6, template_offset, WIPOffset::new(0));
+        }
+        property_offsets.push(builder.end_table(table_wip));
+    }
+    builder.create_vector(&property_offsets)
+}
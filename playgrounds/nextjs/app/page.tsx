@@ -12,14 +12,14 @@ export default function Home() {
         </p>
 
         <div className="mt-8">
-          <div className="container-type-inline-size resize-x overflow-auto border-2 border-dashed border-gray-400 p-4 w-full min-w-[250px] max-w-full">
+          <div className="@container/demo resize-x overflow-auto border-2 border-dashed border-gray-400 p-4 w-full min-w-[250px] max-w-full">
             <div
               className="
                 p-6 rounded-lg 
                 bg-blue-300 text-blue-900 
                 @sm:bg-green-300 @sm:text-green-900
                 @lg:bg-yellow-300 @lg:text-yellow-900
-                @2xl:bg-red-300 @2xl:text-red-900
+                @2xl/demo:bg-red-300 @2xl/demo:text-red-900
                 transition-all duration-300
               "
             >
@@ -29,7 +29,7 @@ export default function Home() {
                   text-lg 
                   @sm:text-xl 
                   @lg:text-2xl 
-                  @2xl:text-3xl
+                  @2xl/demo:text-3xl
                 "
               >
                 I change based on my container!
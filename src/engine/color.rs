@@ -0,0 +1,167 @@
+use lightningcss::printer::PrinterOptions;
+use lightningcss::traits::{Parse, ToCss};
+use lightningcss::values::color::{CssColor, LABColor, RGBA};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OpacityStrategy {
+    /// `color-mix(in oklab, <color> 50%, transparent)`, works for any color.
+    #[default]
+    ColorMix,
+    /// Rewrites hex/rgb and lab/oklch colors with an alpha channel, falling back to
+    /// `color-mix()` for colors that have no literal form (e.g. `currentColor`).
+    Rewrite,
+}
+
+impl OpacityStrategy {
+    pub fn from_config(value: &str) -> Self {
+        match value {
+            "rewrite" => OpacityStrategy::Rewrite,
+            _ => OpacityStrategy::ColorMix,
+        }
+    }
+}
+
+/// Splits `bg-red-500/50` or `bg-red-500/[0.37]` into the color class and an alpha in `0..=1`.
+pub fn split_opacity_modifier(class_name: &str) -> Option<(&str, f32)> {
+    let slash = class_name.rfind('/')?;
+    let (base, modifier) = (&class_name[..slash], &class_name[slash + 1..]);
+    if base.is_empty() {
+        return None;
+    }
+
+    let alpha = if let Some(arbitrary) =
+        modifier.strip_prefix('[').and_then(|m| m.strip_suffix(']'))
+    {
+        match arbitrary.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+            None => arbitrary.parse::<f32>().ok()?,
+        }
+    } else if !modifier.is_empty() && modifier.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        modifier.parse::<f32>().ok()? / 100.0
+    } else {
+        return None;
+    };
+
+    (0.0..=1.0).contains(&alpha).then_some((base, alpha))
+}
+
+/// Applies `alpha` to every declaration in `css` whose value is a color.
+/// Returns `None` when no declaration holds a color.
+pub fn apply_opacity(css: &str, alpha: f32, strategy: OpacityStrategy) -> Option<String> {
+    let mut applied = false;
+    let declarations: Vec<String> = css
+        .split(';')
+        .map(str::trim)
+        .filter(|declaration| !declaration.is_empty())
+        .map(|declaration| {
+            let Some((property, value)) = declaration.split_once(':') else {
+                return declaration.to_string();
+            };
+            match with_alpha(value.trim(), alpha, strategy) {
+                Some(color) => {
+                    applied = true;
+                    format!("{}: {}", property.trim(), color)
+                }
+                None => declaration.to_string(),
+            }
+        })
+        .collect();
+
+    applied.then(|| declarations.join("; "))
+}
+
+fn with_alpha(value: &str, alpha: f32, strategy: OpacityStrategy) -> Option<String> {
+    // Theme colors are only known at runtime, so they are always mixed.
+    if value.starts_with("var(--color-") {
+        return Some(format!(
+            "color-mix(in oklab, {} {}%, transparent)",
+            value,
+            format_percent(alpha)
+        ));
+    }
+    let color = CssColor::parse_string(value).ok()?;
+    if strategy == OpacityStrategy::Rewrite
+        && let Some(rewritten) = rewrite_alpha(&color, alpha)
+    {
+        return rewritten.to_css_string(PrinterOptions::default()).ok();
+    }
+    Some(format!(
+        "color-mix(in oklab, {} {}%, transparent)",
+        value,
+        format_percent(alpha)
+    ))
+}
+
+fn rewrite_alpha(color: &CssColor, alpha: f32) -> Option<CssColor> {
+    match color {
+        CssColor::RGBA(rgba) => Some(CssColor::RGBA(RGBA::new(
+            rgba.red,
+            rgba.green,
+            rgba.blue,
+            rgba.alpha_f32() * alpha,
+        ))),
+        CssColor::LAB(lab) => {
+            let mut lab = **lab;
+            match &mut lab {
+                LABColor::LAB(c) => c.alpha *= alpha,
+                LABColor::LCH(c) => c.alpha *= alpha,
+                LABColor::OKLAB(c) => c.alpha *= alpha,
+                LABColor::OKLCH(c) => c.alpha *= alpha,
+            }
+            Some(CssColor::LAB(Box::new(lab)))
+        }
+        _ => None,
+    }
+}
+
+fn format_percent(alpha: f32) -> String {
+    let percent = (alpha * 100.0 * 1000.0).round() / 1000.0;
+    format!("{}", percent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_opacity_modifiers() {
+        assert_eq!(
+            split_opacity_modifier("bg-red-500/50"),
+            Some(("bg-red-500", 0.5))
+        );
+        assert_eq!(
+            split_opacity_modifier("bg-red-500/[0.37]"),
+            Some(("bg-red-500", 0.37))
+        );
+        assert_eq!(
+            split_opacity_modifier("bg-red-500/[25%]"),
+            Some(("bg-red-500", 0.25))
+        );
+        assert_eq!(split_opacity_modifier("bg-[url(/a.png)]"), None);
+        assert_eq!(split_opacity_modifier("bg-red-500/150"), None);
+    }
+
+    #[test]
+    fn mixes_colors_with_transparent() {
+        assert_eq!(
+            apply_opacity("background-color: #ef4444", 0.5, OpacityStrategy::ColorMix).as_deref(),
+            Some("background-color: color-mix(in oklab, #ef4444 50%, transparent)")
+        );
+        assert_eq!(
+            apply_opacity("padding: 1rem", 0.5, OpacityStrategy::ColorMix),
+            None
+        );
+    }
+
+    #[test]
+    fn rewrites_alpha_channels() {
+        assert_eq!(
+            apply_opacity("color: #ff0000", 0.5, OpacityStrategy::Rewrite).as_deref(),
+            Some("color: #ff000080")
+        );
+        assert_eq!(
+            apply_opacity("color: currentColor", 0.25, OpacityStrategy::Rewrite).as_deref(),
+            Some("color: color-mix(in oklab, currentColor 25%, transparent)")
+        );
+    }
+}
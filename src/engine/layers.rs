@@ -0,0 +1,138 @@
+use lightningcss::rules::{CssRule, CssRuleList};
+use lightningcss::targets::Browsers;
+
+/// `[layers]`: the cascade layer order and the layer each part of the output goes in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CascadeLayers {
+    pub order: Vec<String>,
+    /// Layer for the `[theme]` custom properties.
+    pub theme: String,
+    /// Layer for the preflight, variable defaults and composite properties.
+    pub base: String,
+    /// Layer for the utilities and their keyframes.
+    pub utilities: String,
+}
+
+impl CascadeLayers {
+    /// The `@layer theme, base, …;` statement that fixes the order up front.
+    pub fn statement(&self) -> String {
+        format!("@layer {};\n", self.order.join(", "))
+    }
+
+    /// Wraps `css` in `@layer name { … }`, indenting it one level.
+    pub fn wrap(name: &str, css: &str) -> String {
+        let body: String = css
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    "\n".to_string()
+                } else {
+                    format!("  {}\n", line)
+                }
+            })
+            .collect();
+        format!("@layer {} {{\n{}}}\n", name, body)
+    }
+}
+
+/// Builds lightningcss targets from `[targets]` entries such as `("safari", "15.4")`.
+/// Unknown browsers and malformed versions are skipped.
+pub fn browser_targets<'a>(entries: impl IntoIterator<Item = (&'a str, &'a str)>) -> Browsers {
+    let mut browsers = Browsers::default();
+    for (browser, version) in entries {
+        let Some(version) = parse_version(version) else {
+            continue;
+        };
+        let slot = match browser {
+            "android" => &mut browsers.android,
+            "chrome" => &mut browsers.chrome,
+            "edge" => &mut browsers.edge,
+            "firefox" => &mut browsers.firefox,
+            "ie" => &mut browsers.ie,
+            "ios_saf" => &mut browsers.ios_saf,
+            "opera" => &mut browsers.opera,
+            "safari" => &mut browsers.safari,
+            "samsung" => &mut browsers.samsung,
+            _ => continue,
+        };
+        *slot = Some(version);
+    }
+    browsers
+}
+
+/// Encodes `16.2` as lightningcss does, `major << 16 | minor << 8 | patch`.
+fn parse_version(version: &str) -> Option<u32> {
+    let mut parts = version
+        .trim()
+        .split('.')
+        .map(|part| part.parse::<u32>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    (parts.next().is_none() && minor < 256 && patch < 256)
+        .then_some(major << 16 | minor << 8 | patch)
+}
+
+/// Whether every target understands `@layer` (Chrome 99, Firefox 97, Safari 15.4).
+pub fn supports_cascade_layers(targets: &Browsers) -> bool {
+    let since = |target: Option<u32>, major: u32, minor: u32| {
+        target.is_none_or(|version| version >= (major << 16 | minor << 8))
+    };
+    targets.ie.is_none()
+        && since(targets.android, 99, 0)
+        && since(targets.chrome, 99, 0)
+        && since(targets.edge, 99, 0)
+        && since(targets.firefox, 97, 0)
+        && since(targets.ios_saf, 15, 4)
+        && since(targets.opera, 85, 0)
+        && since(targets.safari, 15, 4)
+        && since(targets.samsung, 18, 0)
+}
+
+/// Replaces each `@layer` block with its rules and drops `@layer` statements. The layers
+/// are written in their declared order, so source order keeps them in the same sequence.
+pub fn flatten_layers<R>(rules: &mut CssRuleList<'_, R>) {
+    rules.0 = std::mem::take(&mut rules.0)
+        .into_iter()
+        .flat_map(|rule| match rule {
+            CssRule::LayerBlock(mut layer) => {
+                flatten_layers(&mut layer.rules);
+                layer.rules.0
+            }
+            CssRule::LayerStatement(_) => Vec::new(),
+            rule => vec![rule],
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightningcss::stylesheet::{ParserOptions, PrinterOptions, StyleSheet};
+
+    #[test]
+    fn reads_browser_targets() {
+        let targets = browser_targets([("safari", "15.4"), ("chrome", "99"), ("netscape", "4")]);
+        assert_eq!(targets.safari, Some(15 << 16 | 4 << 8));
+        assert_eq!(targets.chrome, Some(99 << 16));
+        assert!(supports_cascade_layers(&targets));
+
+        let targets = browser_targets([("safari", "15.3")]);
+        assert!(!supports_cascade_layers(&targets));
+        assert_eq!(parse_version("15.x"), None);
+    }
+
+    #[test]
+    fn flattens_layers_in_order() {
+        let css = "@layer a, b;\n@layer a { .x { color: red } }\n@layer b { .y { color: blue } }";
+        let mut sheet = StyleSheet::parse(css, ParserOptions::default()).unwrap();
+        flatten_layers(&mut sheet.rules);
+        let printed = sheet
+            .to_css(PrinterOptions {
+                minify: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(printed.code, ".x{color:red}.y{color:#00f}");
+    }
+}
@@ -0,0 +1,2104 @@
+mod color;
+mod layers;
+mod preflight;
+mod rule;
+mod variants;
+
+use crate::grouping;
+use color::OpacityStrategy;
+pub use layers::{CascadeLayers, flatten_layers, supports_cascade_layers};
+use lightningcss::properties::{Property, PropertyId};
+use lightningcss::stylesheet::ParserOptions;
+use lightningcss::targets::Browsers;
+use lru::LruCache;
+pub use rule::{GeneratedRule, RuleLayer};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use variants::ArbitraryVariant;
+
+mod styles_generated {
+    #![allow(
+        dead_code,
+        unused_imports,
+        unsafe_op_in_unsafe_fn,
+        mismatched_lifetime_syntaxes
+    )]
+    include!(concat!(env!("OUT_DIR"), "/styles_generated.rs"));
+}
+use styles_generated::style_schema;
+
+pub struct StyleEngine {
+    precompiled: HashMap<String, String>,
+    buffer: Vec<u8>,
+    screens: HashMap<String, String>,
+    /// `[media]` conditions by variant name, e.g. `print` or `motion-reduce`.
+    media: HashMap<String, String>,
+    states: HashMap<String, String>,
+    container_queries: HashMap<String, String>,
+    /// `[functional_variants]` as `(name, template)`, longest name first.
+    functional_variants: Vec<(String, String)>,
+    opacity_strategy: OpacityStrategy,
+    dark_mode: DarkMode,
+    important: Important,
+    keyframes: HashMap<String, String>,
+    variables: HashMap<String, String>,
+    /// `[composites]` as `(property, value)`, sorted by property.
+    composites: Vec<(String, String)>,
+    /// `[selectors]` as `(prefix, selector template)`, longest prefix first.
+    selector_suffixes: Vec<(String, String)>,
+    /// `[theme]` custom properties as `(name, value, dark value)`.
+    theme: Vec<(String, Option<String>, Option<String>)>,
+    /// The `prefix` every utility carries, empty when unset.
+    class_prefix: String,
+    /// The `separator` between variants and utilities.
+    separator: String,
+    layers: Option<CascadeLayers>,
+    targets: Option<Browsers>,
+    preflight: bool,
+    /// Names of the preflight rules left out by `preflight_disable`.
+    preflight_disable: Vec<String>,
+    css_cache: Mutex<LruCache<String, Vec<GeneratedRule>>>,
+}
+
+impl StyleEngine {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let buffer = fs::read(".dx/styles.bin")?;
+        let config = flatbuffers::root::<style_schema::Config>(&buffer)
+            .map_err(|e| format!("Failed to parse styles.bin: {}", e))?;
+
+        let mut precompiled = HashMap::new();
+        if let Some(styles) = config.styles() {
+            for style in styles {
+                let name = style.name();
+                let css = style.css();
+                if !name.is_empty() && !css.is_empty() {
+                    precompiled.insert(
+                        name.to_string(),
+                        css.trim_end().trim_end_matches(';').to_string(),
+                    );
+                }
+            }
+        }
+
+        if let Some(dynamics) = config.dynamics() {
+            for dynamic in dynamics {
+                if let (Some(properties), Some(values)) = (dynamic.properties(), dynamic.values()) {
+                    for value in values {
+                        let key = dynamic.key();
+                        let suffix = value.suffix();
+
+                        let name = if suffix.is_empty() {
+                            key.to_string()
+                        } else {
+                            format!("{}-{}", key, suffix)
+                        };
+                        // A [static] entry with the same name takes precedence.
+                        if !name.is_empty() && !precompiled.contains_key(&name) {
+                            let css = render_dynamic_value(properties, value);
+                            precompiled.insert(name, css);
+                        }
+                    }
+                }
+            }
+        }
+
+        let screens = config.screens().map_or_else(HashMap::new, |s| {
+            s.iter()
+                .map(|screen| (screen.name().to_string(), screen.value().to_string()))
+                .collect()
+        });
+
+        let media = config.media().map_or_else(HashMap::new, |m| {
+            m.iter()
+                .map(|query| (query.name().to_string(), query.value().to_string()))
+                .collect()
+        });
+
+        // A state without `&` (e.g. `:hover`) is appended to the class selector.
+        let states = config.states().map_or_else(HashMap::new, |s| {
+            s.iter()
+                .map(|state| {
+                    let value = state.value();
+                    let template = if value.contains('&') {
+                        value.to_string()
+                    } else {
+                        format!("&{}", value)
+                    };
+                    (state.name().to_string(), template)
+                })
+                .collect()
+        });
+
+        let mut functional_variants: Vec<(String, String)> =
+            config.functional_variants().map_or_else(Vec::new, |f| {
+                f.iter()
+                    .map(|variant| (variant.name().to_string(), variant.template().to_string()))
+                    .collect()
+            });
+        functional_variants.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+
+        let container_queries =
+            config.container_queries().map_or_else(HashMap::new, |c| {
+                c.iter()
+                    .map(|cq| (cq.name().to_string(), cq.value().to_string()))
+                    .collect()
+            });
+
+        let keyframes = config.keyframes().map_or_else(HashMap::new, |k| {
+            k.iter()
+                .map(|keyframe| (keyframe.name().to_string(), keyframe.css().to_string()))
+                .collect()
+        });
+
+        let variables = config.variables().map_or_else(HashMap::new, |v| {
+            v.iter()
+                .map(|variable| (variable.name().to_string(), variable.value().to_string()))
+                .collect()
+        });
+
+        let mut composites: Vec<(String, String)> =
+            config.composites().map_or_else(Vec::new, |c| {
+                c.iter()
+                    .map(|composite| {
+                        (
+                            composite.property().to_string(),
+                            composite.value().to_string(),
+                        )
+                    })
+                    .collect()
+            });
+        composites.sort();
+
+        let mut selector_suffixes: Vec<(String, String)> =
+            config.selectors().map_or_else(Vec::new, |s| {
+                s.iter()
+                    .map(|selector| (selector.prefix().to_string(), selector.suffix().to_string()))
+                    .collect()
+            });
+        selector_suffixes.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+
+        let theme = config.theme().map_or_else(Vec::new, |t| {
+            t.iter()
+                .map(|token| {
+                    (
+                        token.name().to_string(),
+                        token.value().map(str::to_string),
+                        token.dark().map(str::to_string),
+                    )
+                })
+                .collect()
+        });
+
+        let opacity_strategy = config
+            .opacity_strategy()
+            .map_or_else(OpacityStrategy::default, OpacityStrategy::from_config);
+        let dark_mode = config
+            .dark_mode()
+            .map_or_else(DarkMode::default, DarkMode::from_config);
+        let important = match config.important_selector() {
+            Some(selector) if !selector.trim().is_empty() => {
+                Important::Selector(selector.trim().to_string())
+            }
+            _ if config.important() => Important::Declarations,
+            _ => Important::Off,
+        };
+        let layers = config.layers().map(|layers| CascadeLayers {
+            order: layers
+                .order()
+                .map_or_else(Vec::new, |order| order.iter().map(str::to_string).collect()),
+            theme: layers.theme().to_string(),
+            base: layers.base().to_string(),
+            utilities: layers.utilities().to_string(),
+        });
+        let targets = config
+            .targets()
+            .filter(|targets| !targets.is_empty())
+            .map(|targets| {
+                layers::browser_targets(
+                    targets
+                        .iter()
+                        .map(|target| (target.browser(), target.version())),
+                )
+            });
+        let preflight = config.preflight();
+        let preflight_disable = config
+            .preflight_disable()
+            .map_or_else(Vec::new, |names| names.iter().map(str::to_string).collect());
+        let class_prefix = config.prefix().unwrap_or_default().to_string();
+        let separator = config
+            .separator()
+            .filter(|separator| !separator.is_empty())
+            .unwrap_or(":")
+            .to_string();
+
+        Ok(Self {
+            precompiled,
+            buffer,
+            screens,
+            media,
+            states,
+            container_queries,
+            functional_variants,
+            opacity_strategy,
+            dark_mode,
+            important,
+            keyframes,
+            variables,
+            composites,
+            selector_suffixes,
+            theme,
+            class_prefix,
+            separator,
+            layers,
+            targets,
+            preflight,
+            preflight_disable,
+            css_cache: Mutex::new(LruCache::new(NonZeroUsize::new(1000).unwrap())),
+        })
+    }
+
+    pub fn generate_rules_for_class(&self, class_name: &str) -> Option<Vec<GeneratedRule>> {
+        if let Some(cached) = self.css_cache.lock().unwrap().get(class_name) {
+            return Some(cached.clone());
+        }
+
+        let rules = match grouping::expand_group(class_name, &self.separator) {
+            Some(expanded) => {
+                let rules: Vec<GeneratedRule> = expanded
+                    .iter()
+                    .filter_map(|(tokens, utility)| self.generate_rule(tokens, utility))
+                    .collect();
+                (!rules.is_empty()).then_some(rules)
+            }
+            None => self
+                .generate_rule(&[class_name], class_name)
+                .map(|rule| vec![rule]),
+        }?;
+
+        self.css_cache
+            .lock()
+            .unwrap()
+            .put(class_name.to_string(), rules.clone());
+        Some(rules)
+    }
+
+    /// Generates the rule for `utility`, selected by the literal classes `selector_classes`
+    /// (which differ from `utility` for grouped variants).
+    fn generate_rule<S: AsRef<str>>(
+        &self,
+        selector_classes: &[S],
+        utility: &str,
+    ) -> Option<GeneratedRule> {
+        let (prefixes, base_class) = variants::split_variants(utility, &self.separator)?;
+        let (base_class, important) = split_important(base_class);
+        let base_class = strip_class_prefix(base_class, &self.class_prefix)?;
+        let base_class = base_class.as_ref();
+
+        let mut at_rules = Vec::new();
+        let mut media = Vec::new();
+        let mut containers: Vec<(Option<&str>, String)> = Vec::new();
+        let mut selector_templates: Vec<Cow<str>> = Vec::new();
+        let mut layer = RuleLayer::default();
+
+        for prefix in prefixes {
+            if prefix.starts_with('[') {
+                match variants::parse_arbitrary_variant(prefix)? {
+                    ArbitraryVariant::Selector(template) => {
+                        selector_templates.push(template.into())
+                    }
+                    ArbitraryVariant::AtRule(at_rule) => {
+                        layer.conditions += 1;
+                        at_rules.push(at_rule)
+                    }
+                }
+            } else if prefix == "dark" {
+                match &self.dark_mode {
+                    DarkMode::Media => {
+                        layer.conditions += 1;
+                        media.push("(prefers-color-scheme: dark)".to_string())
+                    }
+                    DarkMode::Selector(template) => selector_templates.push(template.into()),
+                }
+            } else if let Some(condition) = self.screen_condition(prefix, &mut layer) {
+                media.push(condition);
+            } else if let Some(condition) = self.media.get(prefix) {
+                layer.conditions += 1;
+                media.push(condition.clone());
+            } else if prefix.starts_with('@') {
+                containers.push(self.container_condition(prefix, &mut layer)?);
+            } else if let Some(template) = self.states.get(prefix) {
+                selector_templates.push(template.into());
+            } else if let Some((state, name)) = prefix.split_once('/')
+                && let Some(template) = self.states.get(state)
+                && let Some(named) = name_group_template(template, name)
+            {
+                selector_templates.push(named.into());
+            } else if let Some(variant) = self.functional_variant(prefix) {
+                match variant? {
+                    ArbitraryVariant::Selector(template) => {
+                        selector_templates.push(template.into())
+                    }
+                    ArbitraryVariant::AtRule(at_rule) => {
+                        layer.conditions += 1;
+                        at_rules.push(at_rule)
+                    }
+                }
+            } else {
+                return None;
+            }
+        }
+        layer.states = selector_templates.len();
+        if !media.is_empty() {
+            let queries = media_queries(&media)?;
+            at_rules.splice(
+                0..0,
+                queries.iter().map(|query| format!("@media {}", query)),
+            );
+        }
+        // Conditions on the same container combine into one `@container` rule, in the
+        // order each container is first mentioned.
+        let mut names: Vec<Option<&str>> = Vec::new();
+        for (name, _) in &containers {
+            if !names.contains(name) {
+                names.push(*name);
+            }
+        }
+        for name in names {
+            let conditions: Vec<&str> = containers
+                .iter()
+                .filter(|(other, _)| *other == name)
+                .map(|(_, condition)| condition.as_str())
+                .collect();
+            at_rules.push(match name {
+                Some(name) => format!("@container {} {}", name, conditions.join(" and ")),
+                None => format!("@container {}", conditions.join(" and ")),
+            });
+        }
+
+        let mut declarations = self
+            .resolve_utility(base_class)
+            .or_else(|| self.generate_opacity_css(base_class))?;
+        let important = important || self.important == Important::Declarations;
+        if important {
+            declarations = mark_important(&declarations);
+        }
+        let class_selector: String = selector_classes
+            .iter()
+            .map(|class| format!(".{}", escape_class_name(class.as_ref())))
+            .collect();
+        let mut selector = selector_templates
+            .iter()
+            .fold(class_selector, |selector, template| {
+                template.replace('&', &selector)
+            });
+        // Like a state, a `[selectors]` value without `&` is appended to the selector.
+        if let Some(template) = self.selector_suffix(base_class) {
+            selector = if template.contains('&') {
+                template.replace('&', &selector)
+            } else {
+                format!("{}{}", selector, template)
+            };
+        }
+        if let Important::Selector(scope) = &self.important {
+            selector = scope_selector(scope, &selector);
+        }
+
+        let keyframes = self.referenced_keyframes(&declarations);
+        let composites = self.referenced_composites(&declarations);
+        let mut variables = self.referenced_variables(&declarations);
+        for (_, value) in self
+            .composites
+            .iter()
+            .filter(|(property, _)| composites.contains(property))
+        {
+            for name in self.referenced_variables(value) {
+                if !variables.contains(&name) {
+                    variables.push(name);
+                }
+            }
+        }
+        Some(GeneratedRule {
+            at_rules,
+            selector,
+            declarations,
+            important,
+            layer,
+            keyframes,
+            variables,
+            composites,
+        })
+    }
+
+    /// Resolves `data-[state=open]` or `data-active` against `[functional_variants]`.
+    /// `None` means no functional variant has that name; `Some(None)` means the value
+    /// does not give a valid selector or at-rule.
+    fn functional_variant(&self, variant: &str) -> Option<Option<ArbitraryVariant>> {
+        self.functional_variants
+            .iter()
+            .find_map(|(name, template)| {
+                let value = variant.strip_prefix(name.as_str())?.strip_prefix('-')?;
+                Some(variants::parse_functional_variant(template, value))
+            })
+    }
+
+    /// Resolves a container variant: `@md`, `@max-md`, `@[17.5rem]` or `@max-[17.5rem]`,
+    /// optionally targeting a named container as in `@md/sidebar`.
+    fn container_condition<'a>(
+        &self,
+        variant: &'a str,
+        layer: &mut RuleLayer,
+    ) -> Option<(Option<&'a str>, String)> {
+        let (query, name) = match variant.split_once('/') {
+            Some((query, name)) if is_container_name(name) => (query, Some(name)),
+            Some(_) => return None,
+            None => (variant, None),
+        };
+        let (query, max) = match query.strip_prefix("@max-") {
+            Some(rest) if !self.container_queries.contains_key(query) => (rest, true),
+            _ => (query.strip_prefix('@')?, false),
+        };
+        let value = match query.strip_prefix('[') {
+            Some(inner) => decode_arbitrary_value(inner.strip_suffix(']')?)?,
+            None => self.container_queries.get(&format!("@{}", query))?.clone(),
+        };
+        if !is_screen_width(&value) {
+            return None;
+        }
+
+        let width = rule::breakpoint_px(&value);
+        if max {
+            layer.container_max_width =
+                Some(layer.container_max_width.map_or(width, |w| w.min(width)));
+            Some((name, format!("(width < {})", value)))
+        } else {
+            layer.container_min_width =
+                Some(layer.container_min_width.map_or(width, |w| w.max(width)));
+            Some((name, format!("(min-width: {})", value)))
+        }
+    }
+
+    /// `@container/sidebar` is `@container` that also names the container.
+    fn generate_named_container_css(&self, class_name: &str) -> Option<String> {
+        let (base, name) = class_name.split_once('/')?;
+        if !base.starts_with("@container") || !is_container_name(name) {
+            return None;
+        }
+        let css = self.precompiled.get(base)?;
+        Some(format!("{}; container-name: {}", css, name))
+    }
+
+    /// Resolves a `[screens]` variant to its media condition. A plain width like `768px`
+    /// is a `min-width` and also has a `max-` variant; any other value is used as written.
+    fn screen_condition(&self, variant: &str, layer: &mut RuleLayer) -> Option<String> {
+        if let Some(value) = self.screens.get(variant) {
+            if !is_screen_width(value) {
+                layer.conditions += 1;
+                return Some(value.clone());
+            }
+            let min_width = rule::breakpoint_px(value);
+            layer.screen_min_width = Some(
+                layer
+                    .screen_min_width
+                    .map_or(min_width, |w| w.max(min_width)),
+            );
+            return Some(format!("(min-width: {})", value));
+        }
+
+        let value = self.screens.get(variant.strip_prefix("max-")?)?;
+        if !is_screen_width(value) {
+            return None;
+        }
+        let max_width = rule::breakpoint_px(value);
+        layer.screen_max_width = Some(
+            layer
+                .screen_max_width
+                .map_or(max_width, |w| w.min(max_width)),
+        );
+        Some(format!("(width < {})", value))
+    }
+
+    /// Renders the `@keyframes` block for a name listed in `[keyframes]`.
+    pub fn keyframes_css(&self, name: &str) -> Option<String> {
+        let css = self.keyframes.get(name)?;
+        let body: String = css
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| format!("  {}\n", line))
+            .collect();
+        Some(format!("@keyframes {} {{\n{}}}\n", name, body))
+    }
+
+    /// The `[layers]` to wrap the output in, if configured.
+    pub fn layers(&self) -> Option<&CascadeLayers> {
+        self.layers.as_ref()
+    }
+
+    /// The `[targets]` browsers, if configured.
+    pub fn targets(&self) -> Option<Browsers> {
+        self.targets
+    }
+
+    /// Renders the `[theme]` custom properties on `:root`, followed by the dark overrides
+    /// under the configured `dark_mode`.
+    pub fn theme_css(&self) -> String {
+        let declarations = |indent: &str, dark: bool| -> String {
+            self.theme
+                .iter()
+                .filter_map(|(name, value, dark_value)| {
+                    let value = if dark { dark_value } else { value };
+                    Some(format!("{}  {}: {};\n", indent, name, value.as_ref()?))
+                })
+                .collect()
+        };
+
+        let mut css = String::new();
+        let light = declarations("", false);
+        if !light.is_empty() {
+            css.push_str(&format!(":root {{\n{}}}\n", light));
+        }
+        let dark = match &self.dark_mode {
+            DarkMode::Media => declarations("  ", true),
+            DarkMode::Selector(_) => declarations("", true),
+        };
+        if dark.is_empty() {
+            return css;
+        }
+        if !css.is_empty() {
+            css.push('\n');
+        }
+        match &self.dark_mode {
+            DarkMode::Media => css.push_str(&format!(
+                "@media (prefers-color-scheme: dark) {{\n  :root {{\n{}  }}\n}}\n",
+                dark
+            )),
+            DarkMode::Selector(template) => css.push_str(&format!(
+                "{} {{\n{}}}\n",
+                theme_dark_selector(template),
+                dark
+            )),
+        }
+        css
+    }
+
+    /// Renders the rule that assembles a `[composites]` property for every selector
+    /// that sets one of its pieces, inside the at-rules those selectors' rules share.
+    /// The property is `!important` when the pieces are, so it applies like they do.
+    pub fn composite_css(
+        &self,
+        property: &str,
+        at_rules: &[String],
+        important: bool,
+        selectors: &[&str],
+    ) -> Option<String> {
+        let (_, value) = self.composites.iter().find(|(name, _)| name == property)?;
+        if selectors.is_empty() {
+            return None;
+        }
+        let mut css = format!(
+            "{} {{\n  {}: {}{};\n}}\n",
+            selectors.join(",\n"),
+            property,
+            value,
+            if important { " !important" } else { "" }
+        );
+        for at_rule in at_rules.iter().rev() {
+            let body: String = css.lines().map(|line| format!("  {}\n", line)).collect();
+            css = format!("{} {{\n{}}}\n", at_rule, body);
+        }
+        Some(css)
+    }
+
+    /// Finds the `[composites]` properties whose pieces the declarations set.
+    fn referenced_composites(&self, declarations: &str) -> Vec<String> {
+        let set: Vec<&str> = declarations
+            .split(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .map(|(property, _)| property.trim())
+            .filter(|property| property.starts_with("--"))
+            .collect();
+        if set.is_empty() {
+            return Vec::new();
+        }
+        self.composites
+            .iter()
+            .filter(|(_, value)| var_references(value).any(|piece| set.contains(&piece)))
+            .map(|(property, _)| property.clone())
+            .collect()
+    }
+
+    /// Renders the built-in preflight stylesheet when `preflight = true`, leaving out the
+    /// rules named in `preflight_disable` and reading fonts and colors from `[theme]`.
+    pub fn preflight_css(&self) -> String {
+        if !self.preflight {
+            return String::new();
+        }
+        let has_token = |name: &str| {
+            self.theme
+                .iter()
+                .any(|(token, value, dark)| token == name && (value.is_some() || dark.is_some()))
+        };
+        preflight::rules()
+            .filter(|(name, _)| {
+                !self
+                    .preflight_disable
+                    .iter()
+                    .any(|disabled| disabled == name)
+            })
+            .map(|(_, css)| format!("{}\n", preflight::resolve_theme(css, has_token)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Renders the defaults of the given `[variables]` as one universal rule.
+    pub fn variable_defaults_css<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> String {
+        let declarations: String = names
+            .into_iter()
+            .filter_map(|name| {
+                let value = self.variables.get(name)?;
+                Some(format!("  {}: {};\n", name, value))
+            })
+            .collect();
+        if declarations.is_empty() {
+            return String::new();
+        }
+        format!("*, ::before, ::after {{\n{}}}\n", declarations)
+    }
+
+    /// Finds the `[variables]` that declarations set or read, including the ones their
+    /// defaults read in turn.
+    fn referenced_variables(&self, declarations: &str) -> Vec<String> {
+        if self.variables.is_empty() {
+            return Vec::new();
+        }
+        let mut names: Vec<String> = Vec::new();
+        let mut pending: Vec<&str> = declarations
+            .split(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .map(|(property, _)| property.trim())
+            .chain(var_references(declarations))
+            .collect();
+        while let Some(name) = pending.pop() {
+            let Some(default) = self.variables.get(name) else {
+                continue;
+            };
+            if names.iter().any(|known| known == name) {
+                continue;
+            }
+            names.push(name.to_string());
+            pending.extend(var_references(default));
+        }
+        names
+    }
+
+    /// Finds the `[keyframes]` names used by `animation` or `animation-name` declarations.
+    fn referenced_keyframes(&self, declarations: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        for declaration in declarations.split(';') {
+            let Some((property, value)) = declaration.split_once(':') else {
+                continue;
+            };
+            if !matches!(property.trim(), "animation" | "animation-name") {
+                continue;
+            }
+            for token in value.split(|ch: char| ch.is_whitespace() || ch == ',') {
+                if self.keyframes.contains_key(token) && !names.iter().any(|name| name == token) {
+                    names.push(token.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    /// The `[selectors]` template of the longest prefix naming the utility, e.g.
+    /// `& > :not([hidden]) ~ :not([hidden])` for `space-x-4` or `-space-x-2`.
+    fn selector_suffix(&self, class_name: &str) -> Option<&str> {
+        let class_name = class_name.strip_prefix('-').unwrap_or(class_name);
+        self.selector_suffixes
+            .iter()
+            .find(|(prefix, _)| {
+                class_name
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+            })
+            .map(|(_, suffix)| suffix.as_str())
+    }
+
+    fn resolve_utility(&self, class_name: &str) -> Option<String> {
+        self.precompiled
+            .get(class_name)
+            .cloned()
+            .or_else(|| self.generate_named_container_css(class_name))
+            .or_else(|| self.generate_dynamic_css(class_name, false))
+            .or_else(|| self.generate_arbitrary_css(class_name, false))
+            .or_else(|| self.generate_negative_css(class_name))
+    }
+
+    /// A leading `-` negates the numeric or length value of any dynamic, generator,
+    /// arbitrary or static utility, e.g. `-top-1/2`, `-m-4`, `-m-[3px]` or `-mx-px`.
+    fn generate_negative_css(&self, class_name: &str) -> Option<String> {
+        let positive = class_name.strip_prefix('-')?;
+        if positive.starts_with('-') {
+            return None;
+        }
+        self.generate_negative_dynamic_css(positive)
+            .or_else(|| self.generate_dynamic_css(positive, true))
+            .or_else(|| self.generate_arbitrary_css(positive, true))
+            .or_else(|| negate_declarations(self.precompiled.get(positive)?))
+            .filter(|css| accepts_negative(css))
+    }
+
+    /// Negates a `[dynamic]` value before its templates are applied.
+    fn generate_negative_dynamic_css(&self, class_name: &str) -> Option<String> {
+        let config = flatbuffers::root::<style_schema::Config>(&self.buffer).ok()?;
+        for dynamic in config.dynamics()? {
+            let Some(rest) = class_name.strip_prefix(dynamic.key()) else {
+                continue;
+            };
+            let suffix = match rest.strip_prefix('-') {
+                Some(suffix) => suffix,
+                None if rest.is_empty() => rest,
+                None => continue,
+            };
+            let (Some(properties), Some(values)) = (dynamic.properties(), dynamic.values()) else {
+                continue;
+            };
+            if let Some(value) = values.iter().find(|value| value.suffix() == suffix) {
+                if let Some(declarations) = value.declarations()
+                    && !declarations.is_empty()
+                {
+                    return declarations
+                        .iter()
+                        .map(|declaration| {
+                            negate_value(declaration.value())
+                                .map(|value| format!("{}: {}", declaration.property(), value))
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .map(|declarations| declarations.join("; "));
+                }
+                let negated = value
+                    .value()
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|part| !part.is_empty())
+                    .map(negate_value)
+                    .collect::<Option<Vec<_>>>()?;
+                return Some(render_dynamic_declarations(properties, &negated.join("; ")));
+            }
+        }
+        None
+    }
+
+    fn generate_opacity_css(&self, class_name: &str) -> Option<String> {
+        let (color_class, alpha) = color::split_opacity_modifier(class_name)?;
+        let css = self.resolve_utility(color_class)?;
+        color::apply_opacity(&css, alpha, self.opacity_strategy)
+    }
+
+    fn generate_dynamic_css(&self, class_name: &str, negative: bool) -> Option<String> {
+        let config = flatbuffers::root::<style_schema::Config>(&self.buffer).ok()?;
+        let generator = config
+            .generators()?
+            .iter()
+            .filter(|generator| {
+                class_name
+                    .strip_prefix(generator.prefix())
+                    .is_some_and(|rest| rest.starts_with('-'))
+            })
+            .max_by_key(|generator| generator.prefix().len())?;
+        let properties = generator.properties()?;
+
+        let value_str = &class_name[generator.prefix().len() + 1..];
+        let (value_str, is_negative) = match value_str.strip_prefix('-') {
+            Some(stripped) => (stripped, true),
+            None => (value_str, false),
+        };
+        let negative = is_negative != negative;
+
+        if generator.max_viewport() > generator.min_viewport() {
+            if negative {
+                return None;
+            }
+            return self
+                .generate_fluid_value(generator, value_str)
+                .map(|css_value| render_declarations(properties, &css_value));
+        }
+
+        let keyword = generator.keywords().and_then(|keywords| {
+            keywords
+                .iter()
+                .find(|keyword| keyword.suffix() == value_str)
+        });
+        let css_value = if let Some(keyword) = keyword {
+            let value = keyword.value().trim().trim_end_matches(';').to_string();
+            if negative {
+                negate_value(&value)?
+            } else {
+                value
+            }
+        } else if generator.fractions()
+            && let Some(percentage) = fraction_percentage(value_str)
+        {
+            if negative {
+                negate_value(&percentage)?
+            } else {
+                percentage
+            }
+        } else {
+            let num_val: f32 = if value_str.is_empty() {
+                1.0
+            } else {
+                value_str.parse::<f32>().ok()?
+            };
+            let sign = if negative { -1.0 } else { 1.0 };
+            let final_value = num_val * generator.multiplier() * sign;
+            // Avoid rendering `-0` for negated zero values.
+            let final_value = if final_value == 0.0 { 0.0 } else { final_value };
+            format!("{}{}", final_value, generator.unit())
+        };
+        Some(render_declarations(properties, &css_value))
+    }
+
+    /// A fluid generator takes two steps, `p-fluid-4-8`, or a keyword naming two steps,
+    /// `text-fluid-2xl` with `"2xl" = "1.5 1.875"`, and interpolates between them
+    /// across the generator's viewport range.
+    fn generate_fluid_value(
+        &self,
+        generator: style_schema::Generator,
+        value_str: &str,
+    ) -> Option<String> {
+        let steps = generator
+            .keywords()
+            .and_then(|keywords| {
+                keywords
+                    .iter()
+                    .find(|keyword| keyword.suffix() == value_str)
+            })
+            .map_or(value_str, |keyword| keyword.value());
+        let mut steps = steps
+            .split(|ch: char| ch == '-' || ch.is_whitespace())
+            .filter(|step| !step.is_empty())
+            .map(|step| step.parse::<f32>().ok());
+        let (Some(Some(from)), Some(Some(to)), None) = (steps.next(), steps.next(), steps.next())
+        else {
+            return None;
+        };
+        fluid_clamp(
+            from * generator.multiplier(),
+            to * generator.multiplier(),
+            generator.unit(),
+            (generator.min_viewport(), generator.max_viewport()),
+        )
+    }
+
+    fn generate_arbitrary_css(&self, class_name: &str, negative: bool) -> Option<String> {
+        let (prefix, raw_value) = split_arbitrary(class_name)?;
+        let value = decode_arbitrary_value(raw_value)?;
+        let hinted = value.split_once(':').and_then(|(name, rest)| {
+            let (_, example) = TYPE_HINTS.iter().find(|(hint, _)| *hint == name)?;
+            Some((*example, rest.trim().to_string()))
+        });
+        let (hint, value) = match hinted {
+            Some((example, rest)) => (Some(example), rest),
+            None => (None, value),
+        };
+        if value.is_empty() {
+            return None;
+        }
+        let value = if negative {
+            negate_value(&value)?
+        } else {
+            value
+        };
+        let config = flatbuffers::root::<style_schema::Config>(&self.buffer).ok()?;
+
+        // Each candidate carries one of its configured values, which tells what a custom
+        // property like `--tw-gradient-from` accepts.
+        let mut candidates: Vec<(&str, Properties, Option<String>)> = Vec::new();
+        if let Some(dynamics) = config.dynamics() {
+            candidates.extend(
+                dynamics
+                    .iter()
+                    .filter(|d| d.key() == prefix)
+                    .filter_map(|d| {
+                        let values: Vec<&str> = d
+                            .values()?
+                            .iter()
+                            .filter_map(|value| value.value().split(';').next())
+                            .map(str::trim)
+                            .filter(|value| !value.is_empty())
+                            .collect();
+                        let sample = values
+                            .iter()
+                            .find(|value| !value.contains("var("))
+                            .or(values.first())
+                            .map(|value| value.to_string());
+                        Some((d.key(), d.properties()?, sample))
+                    }),
+            );
+        }
+        if let Some(generators) = config.generators() {
+            candidates.extend(
+                generators
+                    .iter()
+                    .filter(|g| g.prefix() == prefix)
+                    .filter_map(|g| {
+                        Some((g.prefix(), g.properties()?, Some(format!("1{}", g.unit()))))
+                    }),
+            );
+        }
+        // Properties lightningcss can validate directly come before custom properties.
+        // Utilities sharing a prefix are told apart by their first property, so
+        // `text-[var(--brand)]` is always a color unless a hint like `length:` says otherwise.
+        candidates.sort_by_key(|(_, properties, _)| {
+            (
+                properties
+                    .iter()
+                    .any(|p| matches!(PropertyId::from(p.name()), PropertyId::Custom(_))),
+                properties.iter().next().map(|p| p.name()),
+            )
+        });
+
+        let accepts = |properties: Properties, sample: Option<&str>, value: &str| {
+            properties.iter().all(|p| {
+                let rendered = apply_template(p, value);
+                match PropertyId::from(p.name()) {
+                    PropertyId::Custom(_) => is_valid_custom_value(
+                        &rendered,
+                        sample.map(|sample| apply_template(p, sample)).as_deref(),
+                    ),
+                    _ => is_valid_declaration(p.name(), &rendered),
+                }
+            })
+        };
+        candidates
+            .into_iter()
+            .find(|(_, properties, sample)| {
+                hint.is_none_or(|example| accepts(*properties, sample.as_deref(), example))
+                    && accepts(*properties, sample.as_deref(), &value)
+            })
+            .map(|(_, properties, _)| render_declarations(properties, &value))
+    }
+}
+
+/// How the `dark:` variant is expressed, set by `dark_mode` in styles.toml.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+enum DarkMode {
+    /// `@media (prefers-color-scheme: dark)`.
+    #[default]
+    Media,
+    /// A selector template where `&` stands for the utility, e.g. `.dark &`.
+    Selector(String),
+}
+
+impl DarkMode {
+    fn from_config(value: &str) -> Self {
+        match value.trim() {
+            "media" => DarkMode::Media,
+            "class" => DarkMode::Selector(".dark &".to_string()),
+            selector if selector.contains('&') => DarkMode::Selector(selector.to_string()),
+            selector => DarkMode::Selector(format!("{} &", selector)),
+        }
+    }
+}
+
+/// Names of the custom properties read with `var(--name)` or `var(--name, fallback)`.
+fn var_references(css: &str) -> impl Iterator<Item = &str> {
+    css.match_indices("var(").filter_map(|(i, _)| {
+        let rest = css[i + 4..].trim_start();
+        let len = rest
+            .find(|ch: char| ch == ',' || ch == ')' || ch.is_whitespace())
+            .unwrap_or(rest.len());
+        rest[..len].starts_with("--").then(|| &rest[..len])
+    })
+}
+
+/// The global `important` option in styles.toml.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+enum Important {
+    #[default]
+    Off,
+    /// `important = true`: every declaration gets `!important`.
+    Declarations,
+    /// `important = "#app"`: every rule is scoped under the selector.
+    Selector(String),
+}
+
+/// Strips the `!` modifier from `!p-4` or `p-4!`.
+fn split_important(class_name: &str) -> (&str, bool) {
+    match class_name
+        .strip_prefix('!')
+        .or_else(|| class_name.strip_suffix('!'))
+    {
+        Some(base) if !base.is_empty() => (base, true),
+        _ => (class_name, false),
+    }
+}
+
+/// Adds `!important` to every declaration that does not already have it, including the
+/// ones in nested blocks such as `::-webkit-scrollbar { width: 8px; }`.
+fn mark_important(css: &str) -> String {
+    css.split(';')
+        .map(str::trim)
+        .filter(|piece| !piece.is_empty())
+        .map(|piece| {
+            // A piece may start by closing a block or end by opening one before its
+            // declaration, as in `} ::-webkit-scrollbar-thumb { color: red`.
+            let declaration = piece.rsplit(['{', '}']).next().unwrap_or(piece).trim();
+            if declaration.is_empty() || declaration.ends_with("!important") {
+                piece.to_string()
+            } else {
+                format!("{} !important", piece)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Scopes a selector under `important = "#app"` as `#app :is(selector)`, so ancestors
+/// from templates such as `.dark &` may sit outside the scope. A pseudo-element cannot
+/// go inside `:is()`, so it stays after it.
+fn scope_selector(scope: &str, selector: &str) -> String {
+    let (selector, pseudo_element) = split_pseudo_element(selector);
+    format!("{} :is({}){}", scope, selector, pseudo_element)
+}
+
+/// Splits `.a:hover::before` into `.a:hover` and `::before` at the first `::` outside
+/// brackets, parentheses and escapes.
+fn split_pseudo_element(selector: &str) -> (&str, &str) {
+    let bytes = selector.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'[' | b'(' => depth += 1,
+            b']' | b')' => depth = depth.saturating_sub(1),
+            b':' if depth == 0 && bytes.get(i + 1) == Some(&b':') => return selector.split_at(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    (selector, "")
+}
+
+/// The element carrying the dark theme: the `dark_mode` template without the utility,
+/// e.g. `.dark` for `.dark &`.
+fn theme_dark_selector(template: &str) -> String {
+    let selector = template.replace('&', "");
+    match selector.trim() {
+        "" => ":root".to_string(),
+        selector => selector.to_string(),
+    }
+}
+
+type Properties<'a> =
+    flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<style_schema::Property<'a>>>;
+
+fn apply_template(property: style_schema::Property, value: &str) -> String {
+    match property.template() {
+        Some(template) => template.replace("{value}", value),
+        None => value.to_string(),
+    }
+}
+
+/// Emits one declaration per property, each with the value run through its template.
+fn render_declarations(properties: Properties, value: &str) -> String {
+    properties
+        .iter()
+        .map(|p| format!("{}: {}", p.name(), apply_template(p, value)))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Renders a `[dynamic]` entry: its own declarations when it was written as a table,
+/// otherwise its value applied to the key's properties.
+fn render_dynamic_value(properties: Properties, value: style_schema::DynamicValue) -> String {
+    match value.declarations() {
+        Some(declarations) if !declarations.is_empty() => declarations
+            .iter()
+            .map(|declaration| format!("{}: {}", declaration.property(), declaration.value()))
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => render_dynamic_declarations(properties, value.value()),
+    }
+}
+
+/// Like [`render_declarations`], but a `[dynamic]` value listing one `;`-separated part per
+/// property (`"auto; auto; auto; auto;"` for `inset`) is assigned positionally.
+fn render_dynamic_declarations(properties: Properties, value: &str) -> String {
+    let parts: Vec<&str> = value
+        .split(';')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    if properties.len() > 1 && parts.len() == properties.len() {
+        properties
+            .iter()
+            .zip(parts)
+            .map(|(p, part)| format!("{}: {}", p.name(), apply_template(p, part)))
+            .collect::<Vec<_>>()
+            .join("; ")
+    } else {
+        render_declarations(properties, value.trim_end().trim_end_matches(';'))
+    }
+}
+
+/// Builds `clamp()` for a value going linearly from `from` at the smaller viewport to
+/// `to` at the larger one (viewports in px). Only `px` and `rem` values can be
+/// interpolated against the viewport width.
+fn fluid_clamp(from: f32, to: f32, unit: &str, (min_vw, max_vw): (f32, f32)) -> Option<String> {
+    let px_per_unit = match unit {
+        "px" => 1.0,
+        "rem" => 16.0,
+        _ => return None,
+    };
+    let (min_vw, max_vw) = (min_vw / px_per_unit, max_vw / px_per_unit);
+    let slope = (to - from) / (max_vw - min_vw);
+    let intercept = from - slope * min_vw;
+    let round = |value: f32| (value * 10_000.0).round() / 10_000.0;
+
+    let slope_vw = round(slope * 100.0);
+    let intercept = round(intercept);
+    let preferred = if intercept == 0.0 {
+        format!("{}vw", slope_vw)
+    } else if intercept < 0.0 {
+        format!("{}vw - {}{}", slope_vw, -intercept, unit)
+    } else if slope_vw < 0.0 {
+        format!("{}{} - {}vw", intercept, unit, -slope_vw)
+    } else {
+        format!("{}{} + {}vw", intercept, unit, slope_vw)
+    };
+    Some(format!(
+        "clamp({}{unit}, {}, {}{unit})",
+        round(from.min(to)),
+        preferred,
+        round(from.max(to)),
+        unit = unit
+    ))
+}
+
+/// A container name is a plain identifier such as `sidebar`.
+fn is_container_name(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+/// Whether a `[screens]` value is a bare width rather than a full media condition.
+fn is_screen_width(value: &str) -> bool {
+    value.starts_with(|ch: char| ch.is_ascii_digit() || ch == '.')
+}
+
+/// Joins stacked media conditions into one query, led by the media type if there is one:
+/// `(min-width: 768px)` and `print` become `print and (min-width: 768px)`. `not (…)` and
+/// `or` conditions are parenthesised so they combine with `and`. A negated media type
+/// such as `not print` negates its whole query, so it gets a query of its own and the
+/// other conditions go in a second one nested inside it.
+fn media_queries(conditions: &[String]) -> Option<Vec<String>> {
+    let mut media_type = None;
+    let mut features: Vec<String> = Vec::new();
+    for condition in conditions {
+        let condition = condition.trim();
+        let negated_feature = condition
+            .strip_prefix("not ")
+            .is_some_and(|rest| rest.trim_start().starts_with('('));
+        let feature = if negated_feature || condition.contains(" or ") {
+            format!("({})", condition)
+        } else if condition.starts_with('(') {
+            condition.to_string()
+        } else if media_type
+            .replace(condition)
+            .is_some_and(|other| other != condition)
+        {
+            return None;
+        } else {
+            continue;
+        };
+        if !features.contains(&feature) {
+            features.push(feature);
+        }
+    }
+    let features = features.join(" and ");
+    Some(match media_type {
+        Some(media_type) if media_type.starts_with("not ") && !features.is_empty() => {
+            vec![media_type.to_string(), features]
+        }
+        Some(media_type) if !features.is_empty() => {
+            vec![format!("{} and {}", media_type, features)]
+        }
+        Some(media_type) => vec![media_type.to_string()],
+        None => vec![features],
+    })
+}
+
+/// Strips the configured class prefix from a utility, keeping a leading `-`:
+/// with `dx-`, `dx-p-4` is `p-4` and `-dx-mt-2` is `-mt-2`.
+fn strip_class_prefix<'a>(base_class: &'a str, prefix: &str) -> Option<Cow<'a, str>> {
+    if let Some(rest) = base_class.strip_prefix(prefix) {
+        return Some(rest.into());
+    }
+    let rest = base_class.strip_prefix('-')?.strip_prefix(prefix)?;
+    Some(format!("-{}", rest).into())
+}
+
+/// Converts a fraction such as `1/3` to a percentage (`33.333333%`).
+fn fraction_percentage(value: &str) -> Option<String> {
+    let (numerator, denominator) = value.split_once('/')?;
+    let numerator: f64 = numerator.parse().ok()?;
+    let denominator: f64 = denominator.parse().ok()?;
+    if denominator == 0.0 || numerator < 0.0 || denominator < 0.0 {
+        return None;
+    }
+    let percent = (numerator / denominator * 100.0 * 1e6).round() / 1e6;
+    Some(format!("{}%", percent))
+}
+
+/// Negates a single numeric or length value: `1rem` becomes `-1rem`, `-1px` becomes `1px`
+/// and functions such as `var(--x)` become `calc(-1 * var(--x))`. Keywords, colors and
+/// multi-part values cannot be negated.
+fn negate_value(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || value.contains(char::is_whitespace) && !value.ends_with(')') {
+        return None;
+    }
+
+    let starts_numeric = |v: &str| v.starts_with(|ch: char| ch.is_ascii_digit() || ch == '.');
+    if let Some(positive) = value.strip_prefix('-')
+        && starts_numeric(positive)
+    {
+        return Some(positive.to_string());
+    }
+    if starts_numeric(value) {
+        if value.contains(char::is_whitespace) {
+            return None;
+        }
+        let number_len = value
+            .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+            .unwrap_or(value.len());
+        return match value[..number_len].parse::<f32>() {
+            Ok(0.0) => Some(value.to_string()),
+            Ok(_) => Some(format!("-{}", value)),
+            Err(_) => None,
+        };
+    }
+
+    const NEGATABLE_FUNCTIONS: [&str; 6] = ["calc(", "var(", "clamp(", "min(", "max(", "env("];
+    (NEGATABLE_FUNCTIONS.iter().any(|f| value.starts_with(f)) && value.ends_with(')'))
+        .then(|| format!("calc(-1 * {})", value))
+}
+
+/// Negates every declaration of a static utility, e.g. `margin: 1px` for `-m-px`.
+fn negate_declarations(css: &str) -> Option<String> {
+    css.split(';')
+        .map(str::trim)
+        .filter(|declaration| !declaration.is_empty())
+        .map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            Some(format!("{}: {}", property.trim(), negate_value(value)?))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|declarations| declarations.join("; "))
+}
+
+/// Splits `w-[23px]` into the utility prefix (`w`) and the raw bracket contents (`23px`).
+fn split_arbitrary(class_name: &str) -> Option<(&str, &str)> {
+    let inner = class_name.strip_suffix(']')?;
+    let open = inner.find("-[")?;
+    let prefix = &inner[..open];
+    let value = &inner[open + 2..];
+    if prefix.is_empty() || value.is_empty() {
+        return None;
+    }
+    Some((prefix, value))
+}
+
+/// Turns the bracket contents into a CSS value: `_` becomes a space (`\_` keeps a literal
+/// underscore). Values that could break out of the declaration are rejected.
+fn decode_arbitrary_value(raw: &str) -> Option<String> {
+    if raw.contains([';', '{', '}']) {
+        return None;
+    }
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&'_') => {
+                value.push('_');
+                chars.next();
+            }
+            '_' => value.push(' '),
+            _ => value.push(ch),
+        }
+    }
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn is_valid_declaration(property: &str, value: &str) -> bool {
+    match Property::parse_string(PropertyId::from(property), value, ParserOptions::default()) {
+        Ok(Property::Unparsed(_)) => value.contains("var(") || value.contains("env("),
+        Ok(_) => true,
+        Err(_) => false,
+    }
+}
+
+/// Type hints that pick between utilities sharing a prefix, as in `text-[length:2rem]`,
+/// each with a value of that type the chosen utility must accept.
+const TYPE_HINTS: [(&str, &str); 9] = [
+    ("color", "red"),
+    ("length", "1px"),
+    ("percentage", "50%"),
+    ("number", "1"),
+    ("angle", "1deg"),
+    ("url", "url(a.png)"),
+    ("image", "linear-gradient(red, blue)"),
+    ("shadow", "0 0 1px red"),
+    ("family-name", "serif"),
+];
+
+/// Properties, and the families starting with them, that take negative values. Custom
+/// properties are listed by name, since e.g. `--filter-blur: blur(-4px)` would make the
+/// whole assembled `filter` invalid.
+const NEGATABLE_PROPERTIES: [&str; 29] = [
+    "margin",
+    "inset",
+    "top",
+    "right",
+    "bottom",
+    "left",
+    "translate",
+    "rotate",
+    "scale",
+    "transform",
+    "z-index",
+    "order",
+    "letter-spacing",
+    "word-spacing",
+    "text-indent",
+    "text-underline-offset",
+    "outline-offset",
+    "scroll-margin",
+    "background-position",
+    "--transform-translate",
+    "--transform-rotate",
+    "--transform-skew",
+    "--transform-scale",
+    "--filter-hue-rotate",
+    "--backdrop-hue-rotate",
+    "--tw-gradient-from-position",
+    "--tw-gradient-via-position",
+    "--tw-gradient-to-position",
+    "--space",
+];
+
+/// Whether every declaration of a negated utility is valid and allows negative values,
+/// so `-mt-4` is kept but `-w-1/2` (`width: -50%`) is not.
+fn accepts_negative(css: &str) -> bool {
+    css.split(';')
+        .map(str::trim)
+        .filter(|declaration| !declaration.is_empty())
+        .all(|declaration| {
+            let Some((property, value)) = declaration.split_once(':') else {
+                return false;
+            };
+            let property = property.trim();
+            let negatable = NEGATABLE_PROPERTIES.iter().any(|name| {
+                property
+                    .strip_prefix(name)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+            });
+            negatable && is_valid_declaration(property, value.trim())
+        })
+}
+
+/// Properties whose syntax stands in for a custom property's, picked by which of them
+/// accepts the custom property's configured value.
+const VALUE_PROBES: [&str; 8] = [
+    "color",
+    "width",
+    "rotate",
+    "opacity",
+    "box-shadow",
+    "filter",
+    "transform",
+    "background-image",
+];
+
+/// Custom properties accept anything, so an arbitrary value must parse the same way as
+/// the property's configured `sample` value: `--tw-gradient-from` only takes colors.
+/// A `var()` reference is accepted as long as the value is well-formed.
+fn is_valid_custom_value(value: &str, sample: Option<&str>) -> bool {
+    if value.contains("var(") {
+        return is_balanced(value) && is_valid_declaration("--value", value);
+    }
+    let Some(sample) = sample else {
+        return false;
+    };
+    VALUE_PROBES
+        .iter()
+        .find(|probe| is_valid_declaration(probe, sample))
+        .is_some_and(|probe| is_valid_declaration(probe, value))
+}
+
+/// Whether every bracket and parenthesis outside quotes is closed by its own counterpart.
+fn is_balanced(value: &str) -> bool {
+    let mut open = Vec::new();
+    let mut quote = None;
+    for ch in value.chars() {
+        match (quote, ch) {
+            (Some(q), ch) if ch == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, '(' | '[') => open.push(ch),
+            (None, ')') if open.pop() != Some('(') => return false,
+            (None, ']') if open.pop() != Some('[') => return false,
+            _ => {}
+        }
+    }
+    open.is_empty() && quote.is_none()
+}
+
+/// Points a `.group`/`.peer` state template at a named group, so `group-hover/sidebar`
+/// turns `.group:hover &` into `.group\/sidebar:hover &`.
+fn name_group_template(template: &str, name: &str) -> Option<String> {
+    if name.is_empty() {
+        return None;
+    }
+    for marker in [".group", ".peer"] {
+        let mut search_from = 0;
+        while let Some(found) = template[search_from..].find(marker) {
+            let end = search_from + found + marker.len();
+            let is_whole_word = !template[end..]
+                .starts_with(|ch: char| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+            if is_whole_word {
+                return Some(format!(
+                    "{}\\/{}{}",
+                    &template[..end],
+                    escape_class_name(name),
+                    &template[end..]
+                ));
+            }
+            search_from = end;
+        }
+    }
+    None
+}
+
+/// Escapes a class name for use in a CSS class selector.
+fn escape_class_name(class_name: &str) -> String {
+    let mut escaped = String::with_capacity(class_name.len() + 8);
+    let starts_with_dash = class_name.starts_with('-');
+    for (i, ch) in class_name.chars().enumerate() {
+        match ch {
+            '0'..='9' if i == 0 || (i == 1 && starts_with_dash) => {
+                escaped.push_str(&format!("\\3{} ", ch));
+            }
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => escaped.push(ch),
+            ch if !ch.is_ascii() => escaped.push(ch),
+            ch => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The shipped engine with the screens, container sizes and global options the tests
+    /// rely on pinned, so editing those in styles.toml does not break unrelated tests.
+    fn test_engine() -> StyleEngine {
+        let pairs = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        let mut engine = StyleEngine::new().unwrap();
+        engine.screens = pairs(&[("sm", "640px"), ("md", "768px"), ("lg", "1024px")]);
+        engine.media = pairs(&[("print", "print"), ("not-print", "not print")]);
+        engine.container_queries = pairs(&[("@md", "28rem"), ("@lg", "32rem"), ("@xl", "36rem")]);
+        engine.dark_mode = DarkMode::Media;
+        engine.important = Important::Off;
+        engine.class_prefix.clear();
+        engine.separator = ":".to_string();
+        engine
+    }
+
+    /// The declarations of the first rule generated for `class`.
+    fn declarations(engine: &StyleEngine, class: &str) -> Option<String> {
+        engine
+            .generate_rules_for_class(class)
+            .map(|rules| rules[0].declarations.clone())
+    }
+
+    #[test]
+    fn splits_arbitrary_classes() {
+        assert_eq!(split_arbitrary("w-[23px]"), Some(("w", "23px")));
+        assert_eq!(split_arbitrary("min-w-[250px]"), Some(("min-w", "250px")));
+        assert_eq!(split_arbitrary("bg-[#1da1f2]"), Some(("bg", "#1da1f2")));
+        assert_eq!(split_arbitrary("w-[]"), None);
+        assert_eq!(split_arbitrary("w-4"), None);
+    }
+
+    #[test]
+    fn decodes_arbitrary_values() {
+        assert_eq!(
+            decode_arbitrary_value("1fr_2fr").as_deref(),
+            Some("1fr 2fr")
+        );
+        assert_eq!(decode_arbitrary_value("a\\_b").as_deref(), Some("a_b"));
+        assert_eq!(decode_arbitrary_value("red;color:blue"), None);
+    }
+
+    #[test]
+    fn validates_values_against_properties() {
+        assert!(is_valid_declaration("width", "23px"));
+        assert!(is_valid_declaration("background-color", "#1da1f2"));
+        assert!(is_valid_declaration("width", "var(--size)"));
+        assert!(is_valid_declaration("--tw-gradient-from", "anything"));
+        assert!(!is_valid_declaration("width", "#1da1f2"));
+        assert!(!is_valid_declaration("background-color", "23px"));
+    }
+
+    #[test]
+    fn checks_custom_property_values_against_their_configured_values() {
+        assert!(is_valid_custom_value("#123456", Some("rgb(0 0 0)")));
+        assert!(!is_valid_custom_value("0 0 2px red", Some("rgb(0 0 0)")));
+        assert!(is_valid_custom_value(
+            "0 0 2px red",
+            Some("0 1px 3px 0 rgb(0 0 0 / 0.1)")
+        ));
+        assert!(is_valid_custom_value("blur(3px)", Some("blur(1px)")));
+        assert!(!is_valid_custom_value("foo", Some("1")));
+        assert!(is_valid_custom_value("var(--x)", None));
+        assert!(is_valid_custom_value("calc(var(--x) * 2)", None));
+        assert!(!is_valid_custom_value("[)var(--x)(]", None));
+        assert!(!is_valid_custom_value("(var(--x)", None));
+        assert!(!is_valid_custom_value("var(--x))", None));
+        assert!(!is_valid_custom_value("red", None));
+    }
+
+    #[test]
+    fn matches_arbitrary_values_to_exact_prefixes() {
+        let engine = test_engine();
+        assert_eq!(declarations(&engine, "bg-[foo]"), None);
+        assert_eq!(
+            declarations(&engine, "bg-[color:red]").as_deref(),
+            Some("background-color: red")
+        );
+        assert_eq!(declarations(&engine, "bg-[length:red]"), None);
+        assert_eq!(declarations(&engine, "text-[foo]"), None);
+        assert_eq!(
+            declarations(&engine, "bg-[#123456]").as_deref(),
+            Some("background-color: #123456")
+        );
+        assert_eq!(
+            declarations(&engine, "bg-[var(--brand)]").as_deref(),
+            Some("background-color: var(--brand)")
+        );
+        assert_eq!(
+            declarations(&engine, "shadow-[0_0_2px_red]").as_deref(),
+            Some("--tw-shadow: 0 0 2px red")
+        );
+        assert_eq!(
+            declarations(&engine, "from-[#123456]").as_deref(),
+            Some("--tw-gradient-from: #123456")
+        );
+        assert_eq!(declarations(&engine, "from-[[)var(--x)(]]"), None);
+    }
+
+    #[test]
+    fn picks_between_utilities_sharing_a_prefix() {
+        let engine = test_engine();
+        assert_eq!(
+            declarations(&engine, "text-[var(--brand)]").as_deref(),
+            Some("color: var(--brand)")
+        );
+        assert_eq!(
+            declarations(&engine, "text-[length:var(--brand)]").as_deref(),
+            Some("font-size: var(--brand)")
+        );
+        assert_eq!(
+            declarations(&engine, "text-[color:#123456]").as_deref(),
+            Some("color: #123456")
+        );
+        assert_eq!(
+            declarations(&engine, "text-[2rem]").as_deref(),
+            Some("font-size: 2rem")
+        );
+        assert_eq!(declarations(&engine, "text-[color:2rem]"), None);
+    }
+
+    #[test]
+    fn negates_only_properties_that_accept_negative_values() {
+        let engine = test_engine();
+        assert_eq!(
+            declarations(&engine, "-top-1/2").as_deref(),
+            Some("top: -50%")
+        );
+        assert_eq!(
+            declarations(&engine, "-inset-full").as_deref(),
+            Some("top: -100%; right: -100%; bottom: -100%; left: -100%")
+        );
+        assert_eq!(declarations(&engine, "-w-1/2"), None);
+        assert_eq!(declarations(&engine, "-p-4"), None);
+        assert_eq!(declarations(&engine, "-size-4"), None);
+        assert_eq!(
+            declarations(&engine, "-rotate-45").as_deref(),
+            Some("--transform-rotate: -45deg")
+        );
+        assert_eq!(
+            declarations(&engine, "-hue-rotate-15").as_deref(),
+            Some("--filter-hue-rotate: hue-rotate(-15deg)")
+        );
+        assert!(declarations(&engine, "-space-x-2").is_some());
+        for class in [
+            "-blur-4",
+            "-brightness-50",
+            "-backdrop-opacity-50",
+            "-drop-shadow-2",
+        ] {
+            assert_eq!(declarations(&engine, class), None, "{}", class);
+        }
+    }
+
+    #[test]
+    fn finds_the_composites_a_utility_sets_a_piece_of() {
+        let engine = test_engine();
+        assert_eq!(
+            engine.referenced_composites("--transform-rotate: 45deg"),
+            ["transform"]
+        );
+        assert_eq!(
+            engine.referenced_composites("--filter-blur: blur(4px); --tw-shadow: 0 0 #0000"),
+            ["box-shadow", "filter"]
+        );
+        assert!(engine.referenced_composites("color: red").is_empty());
+    }
+
+    #[test]
+    fn assembles_composites_under_their_rules_at_rules() {
+        let engine = test_engine();
+        let rules = engine.generate_rules_for_class("md:rotate-45").unwrap();
+        assert_eq!(rules[0].composites, ["transform"]);
+
+        let css = engine
+            .composite_css(
+                "transform",
+                &rules[0].at_rules,
+                false,
+                &[&rules[0].selector],
+            )
+            .unwrap();
+        assert!(
+            css.starts_with("@media (min-width: 768px) {\n  .md\\:rotate-45 {\n    transform: ")
+        );
+        assert!(css.ends_with(";\n  }\n}\n"));
+        assert!(
+            engine
+                .composite_css(
+                    "transform",
+                    &[],
+                    false,
+                    &[".rotate-45", ".hover\\:rotate-45:hover"]
+                )
+                .unwrap()
+                .starts_with(".rotate-45,\n.hover\\:rotate-45:hover {\n  transform: ")
+        );
+        assert_eq!(
+            engine.composite_css("color", &[], false, &[".rotate-45"]),
+            None
+        );
+    }
+
+    #[test]
+    fn makes_composites_important_with_their_pieces() {
+        let mut engine = test_engine();
+        let rules = engine.generate_rules_for_class("!rotate-45").unwrap();
+        assert!(rules[0].important);
+        let css = engine
+            .composite_css("transform", &rules[0].at_rules, true, &[&rules[0].selector])
+            .unwrap();
+        assert!(css.starts_with(".\\!rotate-45 {\n  transform: "));
+        assert!(css.ends_with(" !important;\n}\n"));
+        assert!(!engine.generate_rules_for_class("rotate-45").unwrap()[0].important);
+
+        engine.important = Important::Declarations;
+        assert!(engine.generate_rules_for_class("rotate-90").unwrap()[0].important);
+    }
+
+    #[test]
+    fn finds_the_keyframes_an_animation_uses() {
+        let engine = test_engine();
+        let rules = engine.generate_rules_for_class("animate-spin").unwrap();
+        assert_eq!(rules[0].keyframes, ["spin"]);
+        assert!(
+            engine
+                .keyframes_css("spin")
+                .unwrap()
+                .starts_with("@keyframes spin {\n  to {")
+        );
+
+        let rules = engine
+            .generate_rules_for_class("animate-[wiggle_1s_ease-in-out_infinite]")
+            .unwrap();
+        assert!(rules[0].keyframes.is_empty());
+        assert!(
+            engine
+                .referenced_keyframes("animation: wiggle 1s infinite")
+                .is_empty()
+        );
+        assert_eq!(engine.keyframes_css("wiggle"), None);
+    }
+
+    #[test]
+    fn selects_children_for_spacing_and_divide_utilities() {
+        let engine = test_engine();
+        let children = "& > :not([hidden]) ~ :not([hidden])";
+        assert_eq!(engine.selector_suffix("space-x-4"), Some(children));
+        assert_eq!(engine.selector_suffix("-space-x-2"), Some(children));
+        assert_eq!(engine.selector_suffix("divide-red-500"), Some(children));
+        assert_eq!(engine.selector_suffix("spacer-4"), None);
+        assert_eq!(engine.selector_suffix("space-xl"), None);
+
+        let rules = engine.generate_rules_for_class("md:space-y-2").unwrap();
+        assert_eq!(
+            rules[0].selector,
+            ".md\\:space-y-2 > :not([hidden]) ~ :not([hidden])"
+        );
+        assert_eq!(rules[0].at_rules, ["@media (min-width: 768px)"]);
+    }
+
+    #[test]
+    fn appends_selector_suffixes_without_a_placeholder() {
+        let mut engine = test_engine();
+        engine.selector_suffixes = vec![("p".to_string(), ":not(:last-child)".to_string())];
+        let rules = engine.generate_rules_for_class("hover:p-4").unwrap();
+        assert_eq!(rules[0].selector, ".hover\\:p-4:hover:not(:last-child)");
+    }
+
+    #[test]
+    fn renders_the_theme_with_dark_overrides() {
+        let mut engine = test_engine();
+        engine.theme = vec![
+            (
+                "--color-background".to_string(),
+                Some("white".to_string()),
+                Some("black".to_string()),
+            ),
+            ("--radius".to_string(), Some("0.5rem".to_string()), None),
+        ];
+
+        engine.dark_mode = DarkMode::from_config("media");
+        assert_eq!(
+            engine.theme_css(),
+            ":root {\n  --color-background: white;\n  --radius: 0.5rem;\n}\n\n\
+             @media (prefers-color-scheme: dark) {\n  :root {\n    --color-background: black;\n  }\n}\n"
+        );
+
+        engine.dark_mode = DarkMode::from_config("class");
+        assert_eq!(
+            engine.theme_css(),
+            ":root {\n  --color-background: white;\n  --radius: 0.5rem;\n}\n\n\
+             .dark {\n  --color-background: black;\n}\n"
+        );
+
+        engine.theme.clear();
+        assert_eq!(engine.theme_css(), "");
+    }
+
+    #[test]
+    fn keeps_dynamic_declarations_in_source_order() {
+        let engine = test_engine();
+        let rules = engine.generate_rules_for_class("text-5xl").unwrap();
+        assert_eq!(
+            rules[0].declarations,
+            "font-size: 3rem; line-height: 1; letter-spacing: -0.025em"
+        );
+    }
+
+    #[test]
+    fn combines_conditions_on_the_same_container() {
+        let engine = test_engine();
+        let rules = engine
+            .generate_rules_for_class("@md/a:@lg/b:@xl/a:flex")
+            .unwrap();
+        assert_eq!(
+            rules[0].at_rules,
+            [
+                "@container a (min-width: 28rem) and (min-width: 36rem)",
+                "@container b (min-width: 32rem)"
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_identifiers_as_container_names() {
+        assert!(is_container_name("sidebar"));
+        assert!(is_container_name("main-nav_2"));
+        assert!(!is_container_name("2col"));
+        assert!(!is_container_name("x.y"));
+        assert!(!is_container_name(""));
+    }
+
+    #[test]
+    fn combines_media_conditions() {
+        let strings = |list: &[&str]| list.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            media_queries(&strings(&["(min-width: 768px)", "(width < 1280px)"])),
+            Some(strings(&["(min-width: 768px) and (width < 1280px)"]))
+        );
+        assert_eq!(
+            media_queries(&strings(&[
+                "(min-width: 768px)",
+                "print",
+                "(min-width: 768px)"
+            ])),
+            Some(strings(&["print and (min-width: 768px)"]))
+        );
+        assert_eq!(
+            media_queries(&strings(&[
+                "(hover: hover) or (pointer: fine)",
+                "not (pointer: coarse)",
+                "(orientation: landscape)"
+            ])),
+            Some(strings(&[
+                "((hover: hover) or (pointer: fine)) and (not (pointer: coarse)) and (orientation: landscape)"
+            ]))
+        );
+        assert_eq!(
+            media_queries(&strings(&["not print"])),
+            Some(strings(&["not print"]))
+        );
+        assert_eq!(
+            media_queries(&strings(&["(min-width: 768px)", "not print"])),
+            Some(strings(&["not print", "(min-width: 768px)"]))
+        );
+        assert_eq!(media_queries(&strings(&["print", "screen"])), None);
+        assert_eq!(media_queries(&strings(&["print", "not print"])), None);
+    }
+
+    #[test]
+    fn stacks_print_variants_with_screens() {
+        let engine = test_engine();
+        let at_rules = |class: &str| {
+            engine.generate_rules_for_class(class).unwrap()[0]
+                .at_rules
+                .clone()
+        };
+        assert_eq!(
+            at_rules("md:print:hidden"),
+            ["@media print and (min-width: 768px)"]
+        );
+        assert_eq!(at_rules("not-print:hidden"), ["@media not print"]);
+        assert_eq!(
+            at_rules("md:not-print:hidden"),
+            ["@media not print", "@media (min-width: 768px)"]
+        );
+    }
+
+    #[test]
+    fn strips_the_class_prefix() {
+        assert_eq!(strip_class_prefix("p-4", "").as_deref(), Some("p-4"));
+        assert_eq!(strip_class_prefix("dx-p-4", "dx-").as_deref(), Some("p-4"));
+        assert_eq!(
+            strip_class_prefix("-dx-mt-2", "dx-").as_deref(),
+            Some("-mt-2")
+        );
+        assert_eq!(strip_class_prefix("p-4", "dx-"), None);
+    }
+
+    #[test]
+    fn interpolates_fluid_values() {
+        assert_eq!(
+            fluid_clamp(1.0, 2.0, "rem", (640.0, 1280.0)).as_deref(),
+            Some("clamp(1rem, 2.5vw, 2rem)")
+        );
+        assert_eq!(
+            fluid_clamp(1.5, 1.875, "rem", (640.0, 1280.0)).as_deref(),
+            Some("clamp(1.5rem, 1.125rem + 0.9375vw, 1.875rem)")
+        );
+        assert_eq!(
+            fluid_clamp(32.0, 16.0, "px", (320.0, 1280.0)).as_deref(),
+            Some("clamp(16px, 37.3333px - 1.6667vw, 32px)")
+        );
+        assert_eq!(fluid_clamp(1.0, 2.0, "%", (640.0, 1280.0)), None);
+    }
+
+    #[test]
+    fn converts_fractions_to_percentages() {
+        assert_eq!(fraction_percentage("1/2").as_deref(), Some("50%"));
+        assert_eq!(fraction_percentage("1/3").as_deref(), Some("33.333333%"));
+        assert_eq!(fraction_percentage("11/12").as_deref(), Some("91.666667%"));
+        assert_eq!(fraction_percentage("1/0"), None);
+        assert_eq!(fraction_percentage("a/2"), None);
+        assert_eq!(fraction_percentage("2"), None);
+    }
+
+    #[test]
+    fn negates_values() {
+        assert_eq!(negate_value("1rem").as_deref(), Some("-1rem"));
+        assert_eq!(negate_value("50%").as_deref(), Some("-50%"));
+        assert_eq!(negate_value("-1px").as_deref(), Some("1px"));
+        assert_eq!(negate_value("0px").as_deref(), Some("0px"));
+        assert_eq!(
+            negate_value("var(--gap)").as_deref(),
+            Some("calc(-1 * var(--gap))")
+        );
+        assert_eq!(
+            negate_value("calc(100% - 1rem)").as_deref(),
+            Some("calc(-1 * calc(100% - 1rem))")
+        );
+        assert_eq!(negate_value("auto"), None);
+        assert_eq!(negate_value("#fff"), None);
+        assert_eq!(negate_value("rgb(0 0 0)"), None);
+        assert_eq!(negate_value("0 0 2px"), None);
+    }
+
+    #[test]
+    fn marks_declarations_important() {
+        assert_eq!(split_important("!p-4"), ("p-4", true));
+        assert_eq!(split_important("p-4!"), ("p-4", true));
+        assert_eq!(split_important("p-4"), ("p-4", false));
+        assert_eq!(split_important("!"), ("!", false));
+        assert_eq!(
+            mark_important("margin: 0; padding: 1px !important;"),
+            "margin: 0 !important; padding: 1px !important"
+        );
+        assert_eq!(
+            mark_important(
+                "::-webkit-scrollbar { width: 8px; } ::-webkit-scrollbar-thumb { color: red; }"
+            ),
+            "::-webkit-scrollbar { width: 8px !important; } ::-webkit-scrollbar-thumb { color: red !important; }"
+        );
+        assert_eq!(
+            mark_important("::-webkit-scrollbar { display: none; } scrollbar-width: none;"),
+            "::-webkit-scrollbar { display: none !important; } scrollbar-width: none !important"
+        );
+
+        let engine = test_engine();
+        let css = declarations(&engine, "md:!scrollbar-thin").unwrap();
+        let sheet = format!(".x {{ {}; }}", css);
+        assert!(
+            lightningcss::stylesheet::StyleSheet::parse(&sheet, ParserOptions::default()).is_ok(),
+            "{}",
+            css
+        );
+    }
+
+    #[test]
+    fn scopes_rules_under_the_important_selector() {
+        let mut engine = test_engine();
+        engine.important = Important::Selector("#app".to_string());
+        engine.dark_mode = DarkMode::from_config("class");
+        let selector = |class: &str| {
+            engine.generate_rules_for_class(class).unwrap()[0]
+                .selector
+                .clone()
+        };
+        assert_eq!(selector("p-2"), "#app :is(.p-2)");
+        assert_eq!(selector("dark:p-2"), "#app :is(.dark .dark\\:p-2)");
+        assert_eq!(
+            selector("hover:before:p-2"),
+            "#app :is(.hover\\:before\\:p-2:hover)::before"
+        );
+        assert_eq!(
+            selector("[&_span::after]:p-2"),
+            "#app :is(.\\[\\&_span\\:\\:after\\]\\:p-2 span)::after"
+        );
+    }
+
+    #[test]
+    fn splits_off_pseudo_elements() {
+        assert_eq!(
+            split_pseudo_element(".a:hover::before"),
+            (".a:hover", "::before")
+        );
+        assert_eq!(split_pseudo_element(".a\\:\\:b"), (".a\\:\\:b", ""));
+        assert_eq!(
+            split_pseudo_element(".a:is([x='::'])"),
+            (".a:is([x='::'])", "")
+        );
+        assert_eq!(split_pseudo_element(".a"), (".a", ""));
+    }
+
+    #[test]
+    fn finds_var_references() {
+        assert_eq!(
+            var_references("margin: var(--a); color: var( --b, var(--c) )").collect::<Vec<_>>(),
+            vec!["--a", "--b", "--c"]
+        );
+        assert_eq!(var_references("width: 1px").count(), 0);
+    }
+
+    #[test]
+    fn finds_the_dark_theme_element() {
+        assert_eq!(theme_dark_selector(".dark &"), ".dark");
+        assert_eq!(
+            theme_dark_selector("[data-theme=dark] &"),
+            "[data-theme=dark]"
+        );
+        assert_eq!(theme_dark_selector("&"), ":root");
+    }
+
+    #[test]
+    fn reads_dark_mode_config() {
+        assert_eq!(DarkMode::from_config("media"), DarkMode::Media);
+        assert_eq!(
+            DarkMode::from_config("class"),
+            DarkMode::Selector(".dark &".to_string())
+        );
+        assert_eq!(
+            DarkMode::from_config("[data-theme=dark] &"),
+            DarkMode::Selector("[data-theme=dark] &".to_string())
+        );
+        assert_eq!(
+            DarkMode::from_config(".theme-dark"),
+            DarkMode::Selector(".theme-dark &".to_string())
+        );
+    }
+
+    #[test]
+    fn balances_brackets_outside_quotes() {
+        assert!(is_balanced("calc(var(--a) * (1 + 2))"));
+        assert!(is_balanced("[a] 'x)('"));
+        assert!(!is_balanced("[)(]"));
+        assert!(!is_balanced("(a]"));
+        assert!(!is_balanced("'a"));
+    }
+
+    #[test]
+    fn names_group_and_peer_templates() {
+        assert_eq!(
+            name_group_template(".group:hover &", "sidebar").as_deref(),
+            Some(".group\\/sidebar:hover &")
+        );
+        assert_eq!(
+            name_group_template(".peer:checked ~ &", "email").as_deref(),
+            Some(".peer\\/email:checked ~ &")
+        );
+        assert_eq!(name_group_template("&:hover", "sidebar"), None);
+    }
+
+    #[test]
+    fn escapes_class_selectors() {
+        assert_eq!(escape_class_name("md:p-4"), "md\\:p-4");
+        assert_eq!(escape_class_name("w-[23px]"), "w-\\[23px\\]");
+        assert_eq!(escape_class_name("bg-[#1da1f2]"), "bg-\\[\\#1da1f2\\]");
+        assert_eq!(escape_class_name("w-1/2"), "w-1\\/2");
+        assert_eq!(escape_class_name("2xl:p-0.5"), "\\32 xl\\:p-0\\.5");
+    }
+}
@@ -0,0 +1,68 @@
+//! The built-in preflight stylesheet, split into the named rules of `preflight.css`.
+
+const PREFLIGHT: &str = include_str!("preflight.css");
+
+/// The named rules of the preflight stylesheet as `(name, css)`, in order.
+pub fn rules() -> impl Iterator<Item = (&'static str, &'static str)> {
+    PREFLIGHT.split("/* @rule ").skip(1).filter_map(|section| {
+        let (name, css) = section.split_once("*/")?;
+        Some((name.trim(), css.trim()))
+    })
+}
+
+/// Replaces each `theme(--token, fallback)` with `var(--token)` when the theme defines
+/// the token, otherwise with the fallback.
+pub fn resolve_theme(css: &str, has_token: impl Fn(&str) -> bool) -> String {
+    let mut resolved = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("theme(") {
+        resolved.push_str(&rest[..start]);
+        let args = &rest[start + "theme(".len()..];
+        let mut depth = 1usize;
+        let end = args.find(|ch: char| {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        });
+        let Some(end) = end else {
+            resolved.push_str(&rest[start..]);
+            return resolved;
+        };
+
+        let (token, fallback) = args[..end].split_once(',').unwrap_or((&args[..end], ""));
+        let token = token.trim();
+        if has_token(token) {
+            resolved.push_str(&format!("var({})", token));
+        } else {
+            resolved.push_str(fallback.trim());
+        }
+        rest = &args[end + 1..];
+    }
+    resolved.push_str(rest);
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_the_stylesheet_into_named_rules() {
+        let names: Vec<&str> = rules().map(|(name, _)| name).collect();
+        assert_eq!(names.first(), Some(&"box-sizing"));
+        assert!(names.contains(&"lists"));
+        assert!(rules().all(|(_, css)| !css.is_empty() && !css.contains("@rule")));
+    }
+
+    #[test]
+    fn resolves_theme_references() {
+        let css = "a { font-family: theme(--font-sans, system-ui, sans-serif); color: theme(--color-x, rgb(0 0 0)); }";
+        assert_eq!(
+            resolve_theme(css, |token| token == "--font-sans"),
+            "a { font-family: var(--font-sans); color: rgb(0 0 0); }"
+        );
+    }
+}
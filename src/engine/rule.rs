@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+
+/// A single generated style rule, wrapped in zero or more at-rules (outermost first).
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratedRule {
+    pub at_rules: Vec<String>,
+    pub selector: String,
+    pub declarations: String,
+    /// Whether the declarations are `!important`, through a `!` modifier or the global
+    /// `important = true`.
+    pub important: bool,
+    pub layer: RuleLayer,
+    /// Names of the `[keyframes]` the declarations animate with.
+    pub keyframes: Vec<String>,
+    /// Names of the `[variables]` the declarations set or read.
+    pub variables: Vec<String>,
+    /// `[composites]` properties the declarations set a piece of.
+    pub composites: Vec<String>,
+}
+
+impl GeneratedRule {
+    /// Cascade order: by layer, then by wrappers so identical ones end up adjacent,
+    /// then by selector so the output is stable.
+    pub fn cascade_cmp(&self, other: &Self) -> Ordering {
+        self.layer
+            .cascade_cmp(&other.layer)
+            .then_with(|| self.at_rules.cmp(&other.at_rules))
+            .then_with(|| self.selector.cmp(&other.selector))
+    }
+}
+
+/// Where a rule sits in the cascade: base utilities, then state variants, then screens
+/// by ascending `min-width`, then `max-*` screens by descending width, then container
+/// queries by ascending `min-width` and `max-*` container queries by descending width.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RuleLayer {
+    pub container_min_width: Option<f32>,
+    pub container_max_width: Option<f32>,
+    pub screen_max_width: Option<f32>,
+    pub screen_min_width: Option<f32>,
+    /// Wrappers other than screens and container queries, e.g. `@supports`.
+    pub conditions: usize,
+    /// Number of selector variants (states, `dark`, arbitrary selectors).
+    pub states: usize,
+}
+
+impl RuleLayer {
+    fn cascade_cmp(&self, other: &Self) -> Ordering {
+        cmp_width(self.container_min_width, other.container_min_width)
+            .then_with(|| cmp_max_width(self.container_max_width, other.container_max_width))
+            .then_with(|| cmp_max_width(self.screen_max_width, other.screen_max_width))
+            .then_with(|| cmp_width(self.screen_min_width, other.screen_min_width))
+            .then_with(|| self.conditions.cmp(&other.conditions))
+            .then_with(|| self.states.cmp(&other.states))
+    }
+}
+
+fn cmp_width(a: Option<f32>, b: Option<f32>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// Narrower `max-width` rules come later so they win where they overlap.
+fn cmp_max_width(a: Option<f32>, b: Option<f32>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// Converts a breakpoint like `768px` or `48rem` to pixels for ordering.
+/// Unknown units sort after every known breakpoint.
+pub fn breakpoint_px(value: &str) -> f32 {
+    let value = value.trim();
+    let (number, scale) = if let Some(px) = value.strip_suffix("px") {
+        (px, 1.0)
+    } else if let Some(rem) = value.strip_suffix("rem") {
+        (rem, 16.0)
+    } else if let Some(em) = value.strip_suffix("em") {
+        (em, 16.0)
+    } else {
+        (value, 1.0)
+    };
+    number
+        .trim()
+        .parse::<f32>()
+        .map_or(f32::MAX, |number| number * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(screen: Option<f32>, container: Option<f32>, states: usize) -> RuleLayer {
+        RuleLayer {
+            container_min_width: container,
+            container_max_width: None,
+            screen_max_width: None,
+            screen_min_width: screen,
+            conditions: 0,
+            states,
+        }
+    }
+
+    #[test]
+    fn orders_layers_for_the_cascade() {
+        let mut layers = vec![
+            layer(None, Some(320.0), 0),
+            layer(Some(1024.0), None, 0),
+            layer(Some(640.0), None, 1),
+            layer(Some(640.0), None, 0),
+            layer(None, None, 1),
+            layer(None, None, 0),
+        ];
+        layers.sort_by(RuleLayer::cascade_cmp);
+        assert_eq!(
+            layers,
+            vec![
+                layer(None, None, 0),
+                layer(None, None, 1),
+                layer(Some(640.0), None, 0),
+                layer(Some(640.0), None, 1),
+                layer(Some(1024.0), None, 0),
+                layer(None, Some(320.0), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn orders_max_width_screens_from_widest() {
+        let max = |width: f32| RuleLayer {
+            screen_max_width: Some(width),
+            ..RuleLayer::default()
+        };
+        let mut layers = vec![max(768.0), layer(Some(1024.0), None, 0), max(1280.0)];
+        layers.sort_by(RuleLayer::cascade_cmp);
+        assert_eq!(
+            layers,
+            vec![layer(Some(1024.0), None, 0), max(1280.0), max(768.0)]
+        );
+    }
+
+    #[test]
+    fn converts_breakpoints_to_pixels() {
+        assert_eq!(breakpoint_px("768px"), 768.0);
+        assert_eq!(breakpoint_px("20rem"), 320.0);
+        assert_eq!(breakpoint_px("calc(1px)"), f32::MAX);
+    }
+}
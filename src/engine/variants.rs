@@ -0,0 +1,186 @@
+use super::decode_arbitrary_value;
+use lightningcss::rules::CssRule;
+use lightningcss::stylesheet::{ParserOptions, StyleSheet};
+
+/// A bracketed variant such as `[&>*]` or `[@supports(display:grid)]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArbitraryVariant {
+    /// A selector template where `&` stands for the utility's selector.
+    Selector(String),
+    /// An at-rule prelude the rule is wrapped in, e.g. `@supports (display:grid)`.
+    AtRule(String),
+}
+
+/// Splits `md:[&>*]:hover:p-2` into its variants and the base utility, with `separator`
+/// between them.
+///
+/// Separators inside brackets or parentheses do not separate variants, so arbitrary
+/// values and variants may contain them. Returns `None` for unbalanced brackets.
+pub fn split_variants<'a>(class_name: &'a str, separator: &str) -> Option<(Vec<&'a str>, &'a str)> {
+    let mut variants = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, ch) in class_name.char_indices() {
+        match ch {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.checked_sub(1)?,
+            _ if depth == 0 && i >= start && class_name[i..].starts_with(separator) => {
+                variants.push(&class_name[start..i]);
+                start = i + separator.len();
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+
+    let base = &class_name[start..];
+    if base.is_empty() || variants.iter().any(|variant| variant.is_empty()) {
+        return None;
+    }
+    Some((variants, base))
+}
+
+/// Parses and validates a bracketed variant. `_` stands for a space, as in arbitrary values.
+pub fn parse_arbitrary_variant(variant: &str) -> Option<ArbitraryVariant> {
+    let inner = variant.strip_prefix('[')?.strip_suffix(']')?;
+    let inner = decode_arbitrary_value(inner)?;
+
+    if let Some(rest) = inner.strip_prefix('@') {
+        let name_len = rest
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '-'))
+            .unwrap_or(rest.len());
+        let (name, condition) = rest.split_at(name_len);
+        if name.is_empty() {
+            return None;
+        }
+        let at_rule = format!("@{} {}", name, condition.trim())
+            .trim_end()
+            .to_string();
+        is_valid_at_rule(&at_rule).then_some(ArbitraryVariant::AtRule(at_rule))
+    } else if inner.contains('&') {
+        is_valid_selector(&inner).then_some(ArbitraryVariant::Selector(inner))
+    } else {
+        None
+    }
+}
+
+/// Fills a `[functional_variants]` template with the variant's value, either bracketed
+/// (`[state=open]`, `_` for a space) or bare (`active`), and validates the result.
+pub fn parse_functional_variant(template: &str, value: &str) -> Option<ArbitraryVariant> {
+    let value = match value.strip_prefix('[') {
+        Some(inner) => decode_arbitrary_value(inner.strip_suffix(']')?)?,
+        None if !value.is_empty() && !value.contains(['[', ']']) => value.to_string(),
+        None => return None,
+    };
+    let filled = template.replace("{value}", &value);
+    if filled.starts_with('@') {
+        is_valid_at_rule(&filled).then_some(ArbitraryVariant::AtRule(filled))
+    } else {
+        is_valid_selector(&filled).then_some(ArbitraryVariant::Selector(filled))
+    }
+}
+
+fn is_valid_selector(template: &str) -> bool {
+    let css = format!("{} {{}}", template.replace('&', ".x"));
+    StyleSheet::parse(&css, ParserOptions::default())
+        .is_ok_and(|sheet| matches!(sheet.rules.0.as_slice(), [CssRule::Style(_)]))
+}
+
+fn is_valid_at_rule(at_rule: &str) -> bool {
+    let css = format!("{} {{ .x {{ color: red }} }}", at_rule);
+    StyleSheet::parse(&css, ParserOptions::default()).is_ok_and(|sheet| {
+        matches!(
+            sheet.rules.0.as_slice(),
+            [CssRule::Media(_)
+                | CssRule::Supports(_)
+                | CssRule::Container(_)
+                | CssRule::Scope(_)
+                | CssRule::StartingStyle(_)
+                | CssRule::LayerBlock(_)]
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_top_level_colons_only() {
+        assert_eq!(split_variants("p-4", ":"), Some((vec![], "p-4")));
+        assert_eq!(
+            split_variants("md:hover:p-4", ":"),
+            Some((vec!["md", "hover"], "p-4"))
+        );
+        assert_eq!(
+            split_variants("[@supports(display:grid)]:grid", ":"),
+            Some((vec!["[@supports(display:grid)]"], "grid"))
+        );
+        assert_eq!(
+            split_variants("[&:nth-child(3)]:bg-[color:red]", ":"),
+            Some((vec!["[&:nth-child(3)]"], "bg-[color:red]"))
+        );
+        assert_eq!(split_variants("[&>*:p-2", ":"), None);
+        assert_eq!(split_variants("md::p-2", ":"), None);
+        assert_eq!(
+            split_variants("md__[&_a]__p-4", "__"),
+            Some((vec!["md", "[&_a]"], "p-4"))
+        );
+    }
+
+    #[test]
+    fn parses_selector_variants() {
+        assert_eq!(
+            parse_arbitrary_variant("[&>*]"),
+            Some(ArbitraryVariant::Selector("&>*".to_string()))
+        );
+        assert_eq!(
+            parse_arbitrary_variant("[.sidebar_&]"),
+            Some(ArbitraryVariant::Selector(".sidebar &".to_string()))
+        );
+        assert_eq!(parse_arbitrary_variant("[>*]"), None);
+        assert_eq!(parse_arbitrary_variant("[&>>]"), None);
+    }
+
+    #[test]
+    fn fills_functional_variants() {
+        assert_eq!(
+            parse_functional_variant("&[data-{value}]", "[state=open]"),
+            Some(ArbitraryVariant::Selector("&[data-state=open]".to_string()))
+        );
+        assert_eq!(
+            parse_functional_variant("&[data-{value}]", "active"),
+            Some(ArbitraryVariant::Selector("&[data-active]".to_string()))
+        );
+        assert_eq!(
+            parse_functional_variant("@supports ({value})", "[display:grid]"),
+            Some(ArbitraryVariant::AtRule(
+                "@supports (display:grid)".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_functional_variant("&[data-{value}]", "[state=]]"),
+            None
+        );
+        assert_eq!(parse_functional_variant("&[data-{value}]", ""), None);
+    }
+
+    #[test]
+    fn parses_at_rule_variants() {
+        assert_eq!(
+            parse_arbitrary_variant("[@supports(display:grid)]"),
+            Some(ArbitraryVariant::AtRule(
+                "@supports (display:grid)".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_arbitrary_variant("[@media_(min-width:900px)]"),
+            Some(ArbitraryVariant::AtRule(
+                "@media (min-width:900px)".to_string()
+            ))
+        );
+        assert_eq!(parse_arbitrary_variant("[@bogus_foo]"), None);
+    }
+}
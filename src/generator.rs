@@ -1,7 +1,11 @@
-use crate::engine::StyleEngine;
+use crate::engine::{
+    CascadeLayers, GeneratedRule, StyleEngine, flatten_layers, supports_cascade_layers,
+};
+use colored::Colorize;
 use lightningcss::stylesheet::{ParserOptions, PrinterOptions, StyleSheet};
+use lightningcss::targets::Browsers;
 use rayon::prelude::*;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -13,33 +17,298 @@ pub fn generate_css(
 ) {
     let is_production = std::env::var("DX_ENV").map_or(false, |v| v == "production");
 
-    let mut sorted_class_names: Vec<_> = class_names.iter().collect();
-    sorted_class_names.sort_unstable();
-
-    let css_rules: Vec<String> = sorted_class_names
+    let mut css_rules: Vec<GeneratedRule> = class_names
         .par_iter()
-        .filter_map(|class_name| engine.generate_css_for_class(class_name))
+        .filter_map(|class_name| engine.generate_rules_for_class(class_name))
+        .flatten()
         .collect();
 
-    if css_rules.is_empty() {
+    let theme = engine.theme_css();
+    let preflight = engine.preflight_css();
+    if css_rules.is_empty() && theme.is_empty() && preflight.is_empty() {
         fs::write(output_path, "").expect("Failed to write empty CSS file");
         return;
     }
 
-    let css_content = css_rules.join("\n\n");
+    css_rules.sort_by(GeneratedRule::cascade_cmp);
+    let mut css_content = render_rules(&css_rules);
+
+    let variables: BTreeSet<&str> = css_rules
+        .iter()
+        .flat_map(|rule| rule.variables.iter().map(String::as_str))
+        .collect();
+    let mut base = preflight;
+    let variable_defaults = engine.variable_defaults_css(variables);
+    if !variable_defaults.is_empty() {
+        if !base.is_empty() {
+            base.push('\n');
+        }
+        base.push_str(&variable_defaults);
+    }
 
-    if is_production {
-        let stylesheet =
-            StyleSheet::parse(&css_content, ParserOptions::default()).expect("Failed to parse CSS");
-        let minified_css = stylesheet
-            .to_css(PrinterOptions {
-                minify: true,
-                ..Default::default()
-            })
-            .expect("Failed to minify CSS");
-        fs::write(output_path, minified_css.code.as_bytes())
-            .expect("Failed to write minified CSS");
+    // One rule per composite property, set of at-rules and importance, listing every
+    // selector that sets a piece of it under the same conditions, ahead of the utilities
+    // so that e.g. `transform-none` still overrides it.
+    let mut composites: BTreeMap<(&str, &[String], bool), Vec<&str>> = BTreeMap::new();
+    for rule in &css_rules {
+        for property in &rule.composites {
+            let selectors = composites
+                .entry((property, &rule.at_rules, rule.important))
+                .or_default();
+            if !selectors.contains(&rule.selector.as_str()) {
+                selectors.push(&rule.selector);
+            }
+        }
+    }
+    for ((property, at_rules, important), selectors) in composites {
+        if let Some(composite_css) = engine.composite_css(property, at_rules, important, &selectors)
+        {
+            if !base.is_empty() {
+                base.push('\n');
+            }
+            base.push_str(&composite_css);
+        }
+    }
+
+    css_content.push_str(&keyframes_css(engine, &css_rules));
+
+    let css_content = match engine.layers() {
+        Some(layers) => layered_css(layers, &theme, &base, &css_content),
+        None => [theme.as_str(), base.as_str(), css_content.as_str()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    // lightningcss lowers the output for the `[targets]` browsers, flattening the layers
+    // again when one of them predates them.
+    let targets = engine.targets();
+    let flatten = engine.layers().is_some()
+        && targets.is_some_and(|targets| !supports_cascade_layers(&targets));
+    // A utility lightningcss cannot parse must not stop the watcher, so the CSS is
+    // written as generated instead.
+    let css_content = if is_production || targets.is_some() {
+        print_css(&css_content, targets, flatten, is_production).unwrap_or_else(|e| {
+            eprintln!(
+                "{} Failed to process CSS, writing it unprocessed: {}",
+                "Error:".red(),
+                e
+            );
+            css_content
+        })
     } else {
-        fs::write(output_path, css_content).expect("Failed to write CSS file");
+        css_content
+    };
+    fs::write(output_path, css_content).expect("Failed to write CSS file");
+}
+
+/// Prints the CSS through lightningcss for `targets`, flattening cascade layers first
+/// when `flatten` is set.
+fn print_css(
+    css: &str,
+    targets: Option<Browsers>,
+    flatten: bool,
+    minify: bool,
+) -> Result<String, String> {
+    let mut stylesheet =
+        StyleSheet::parse(css, ParserOptions::default()).map_err(|e| e.to_string())?;
+    if flatten {
+        flatten_layers(&mut stylesheet.rules);
+    }
+    stylesheet
+        .to_css(PrinterOptions {
+            minify,
+            targets: targets.map(Into::into).unwrap_or_default(),
+            ..Default::default()
+        })
+        .map(|printed| printed.code)
+        .map_err(|e| e.to_string())
+}
+
+/// The `@keyframes` blocks the rules animate with, each emitted once however many
+/// utilities use it.
+fn keyframes_css(engine: &StyleEngine, rules: &[GeneratedRule]) -> String {
+    let names: BTreeSet<&str> = rules
+        .iter()
+        .flat_map(|rule| rule.keyframes.iter().map(String::as_str))
+        .collect();
+    names
+        .into_iter()
+        .filter_map(|name| engine.keyframes_css(name))
+        .map(|css| format!("\n{}", css))
+        .collect()
+}
+
+/// Declares the layer order, then puts each part of the output in its layer. The blocks
+/// follow the declared order, so flattening them keeps it; layers missing from the order
+/// come last, as they would in the cascade.
+fn layered_css(layers: &CascadeLayers, theme: &str, base: &str, utilities: &str) -> String {
+    let parts = [
+        (layers.theme.as_str(), theme),
+        (layers.base.as_str(), base),
+        (layers.utilities.as_str(), utilities),
+    ];
+    let mut names: Vec<&str> = layers.order.iter().map(String::as_str).collect();
+    for (name, _) in parts {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let mut css = layers.statement();
+    for name in names {
+        let body = parts
+            .iter()
+            .filter(|(layer, part)| *layer == name && !part.is_empty())
+            .map(|(_, part)| *part)
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !body.is_empty() {
+            css.push('\n');
+            css.push_str(&CascadeLayers::wrap(name, &body));
+        }
+    }
+    css
+}
+
+/// Renders sorted rules, sharing one block between consecutive rules with the same
+/// at-rule wrappers instead of repeating the wrapper for every rule.
+fn render_rules(rules: &[GeneratedRule]) -> String {
+    let mut css = String::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut first_in_block = true;
+
+    for rule in rules {
+        let shared = open
+            .iter()
+            .zip(&rule.at_rules)
+            .take_while(|(open, at_rule)| **open == at_rule.as_str())
+            .count();
+        while open.len() > shared {
+            open.pop();
+            css.push_str(&"  ".repeat(open.len()));
+            css.push_str("}\n");
+            first_in_block = false;
+        }
+
+        for at_rule in &rule.at_rules[shared..] {
+            if !first_in_block {
+                css.push('\n');
+            }
+            css.push_str(&"  ".repeat(open.len()));
+            css.push_str(at_rule);
+            css.push_str(" {\n");
+            open.push(at_rule);
+            first_in_block = true;
+        }
+
+        if !first_in_block {
+            css.push('\n');
+        }
+        let indent = "  ".repeat(open.len());
+        css.push_str(&format!(
+            "{indent}{} {{\n{indent}  {};\n{indent}}}\n",
+            rule.selector, rule.declarations
+        ));
+        first_in_block = false;
+    }
+
+    while !open.is_empty() {
+        open.pop();
+        css.push_str(&"  ".repeat(open.len()));
+        css.push_str("}\n");
+    }
+    css
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emits_shared_keyframes_once() {
+        let engine = StyleEngine::new().unwrap();
+        let rules: Vec<GeneratedRule> = ["animate-spin", "hover:animate-spin", "md:animate-spin"]
+            .into_iter()
+            .filter_map(|class| engine.generate_rules_for_class(class))
+            .flatten()
+            .collect();
+        assert_eq!(rules.len(), 3);
+
+        let css = keyframes_css(&engine, &rules);
+        assert_eq!(css.matches("@keyframes").count(), 1);
+        assert!(css.starts_with("\n@keyframes spin {\n"));
+    }
+
+    #[test]
+    fn lowers_max_screens_for_the_targets() {
+        let engine = StyleEngine::new().unwrap();
+        let rules = engine.generate_rules_for_class("max-md:flex").unwrap();
+        assert!(rules[0].at_rules[0].starts_with("@media (width < "));
+
+        let targets = Browsers {
+            safari: Some(16 << 16 | 2 << 8),
+            ..Browsers::default()
+        };
+        let css = print_css(&render_rules(&rules), Some(targets), false, true).unwrap();
+        assert!(css.starts_with("@media not (min-width:"), "{}", css);
+        assert!(
+            print_css(&render_rules(&rules), None, false, true)
+                .unwrap()
+                .starts_with("@media (width<")
+        );
+    }
+
+    #[test]
+    fn reports_css_lightningcss_cannot_parse() {
+        let css = ".a { ::-webkit-scrollbar { width: 8px !important; } !important; }";
+        assert!(print_css(css, None, false, false).is_err());
+    }
+
+    #[test]
+    fn flattens_layers_in_their_configured_order() {
+        let layers = CascadeLayers {
+            order: vec!["theme".into(), "utilities".into(), "base".into()],
+            theme: "theme".into(),
+            base: "base".into(),
+            utilities: "utilities".into(),
+        };
+        let css = layered_css(
+            &layers,
+            ":root { --a: 1 }",
+            ".b { color: red }",
+            ".u { color: blue }",
+        );
+        assert!(css.starts_with("@layer theme, utilities, base;\n"));
+
+        let targets = Browsers {
+            safari: Some(15 << 16 | 3 << 8),
+            ..Browsers::default()
+        };
+        assert_eq!(
+            print_css(&css, Some(targets), true, true).unwrap(),
+            ":root{--a:1}.u{color:#00f}.b{color:red}"
+        );
+    }
+
+    #[test]
+    fn puts_layers_missing_from_the_order_last() {
+        let layers = CascadeLayers {
+            order: vec!["utilities".into()],
+            theme: "base".into(),
+            base: "base".into(),
+            utilities: "utilities".into(),
+        };
+        let css = layered_css(
+            &layers,
+            ".t { color: red }",
+            ".b { color: red }",
+            ".u { color: red }",
+        );
+        let utilities = css.find("@layer utilities {").unwrap();
+        let base = css.find("@layer base {").unwrap();
+        assert!(utilities < base);
+        assert_eq!(css.matches("@layer base {").count(), 1);
     }
 }
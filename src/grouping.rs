@@ -0,0 +1,259 @@
+//! Variant grouping: `lg(px-2 p-10)` is shorthand for `lg:px-2 lg:p-10`.
+//!
+//! The browser still sees the whitespace-separated tokens (`lg(px-2` and `p-10)`), so
+//! each expanded utility is paired with the literal token its CSS has to select.
+
+use std::ops::Range;
+
+/// Splits a class attribute on whitespace, keeping parenthesised groups together.
+/// Parentheses inside brackets, as in `bg-[url(a.png)]`, are part of an arbitrary value.
+pub fn split_class_list(class_list: &str) -> Vec<String> {
+    let mut class_names = Vec::new();
+    let mut pending: Vec<&str> = Vec::new();
+    let mut depth = 0isize;
+
+    for token in class_list.split_whitespace() {
+        pending.push(token);
+        let mut brackets = 0usize;
+        for ch in token.chars() {
+            match ch {
+                '[' => brackets += 1,
+                ']' => brackets = brackets.saturating_sub(1),
+                '(' if brackets == 0 => depth += 1,
+                ')' if brackets == 0 => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth <= 0 {
+            class_names.push(pending.join(" "));
+            pending.clear();
+            depth = 0;
+        }
+    }
+    // An unclosed group is not a group at all.
+    class_names.extend(pending.into_iter().map(str::to_string));
+    class_names
+}
+
+/// Expands a grouped class into `(literal tokens, utility)` pairs, e.g.
+/// `md(hover(bg-red-500 text-white))` into `(["md(hover(bg-red-500"], "md:hover:bg-red-500")`
+/// and `(["md(hover(bg-red-500", "text-white))"], "md:hover:text-white")`, joining
+/// variants with `separator`.
+///
+/// A token inside a group is selected together with the tokens that open its groups,
+/// since a bare `p-10)` would also match the closing token of any other group.
+///
+/// Returns `None` when the class is not a well-formed group.
+pub fn expand_group(class_name: &str, separator: &str) -> Option<Vec<(Vec<String>, String)>> {
+    if !is_group(class_name) {
+        return None;
+    }
+
+    let mut utilities = Vec::new();
+    let mut pos = 0;
+    parse_items(
+        class_name,
+        &mut pos,
+        &[],
+        &[],
+        false,
+        separator,
+        &mut utilities,
+    )?;
+
+    let tokens: Vec<Range<usize>> = class_name
+        .split_whitespace()
+        .map(|token| {
+            let start = token.as_ptr() as usize - class_name.as_ptr() as usize;
+            start..start + token.len()
+        })
+        .collect();
+
+    utilities
+        .into_iter()
+        .map(|(span, utility, openers)| {
+            let find = |offset: usize| tokens.iter().find(|token| token.contains(&offset));
+            let mut selected: Vec<&Range<usize>> = Vec::new();
+            for token in openers.into_iter().chain([span.start]).map(find) {
+                let token = token?;
+                if !selected.contains(&token) {
+                    selected.push(token);
+                }
+            }
+            let selected = selected
+                .into_iter()
+                .map(|token| class_name[token.clone()].to_string())
+                .collect();
+            Some((selected, utility))
+        })
+        .collect()
+}
+
+fn is_group(class_name: &str) -> bool {
+    let mut depth = 0usize;
+    for ch in class_name.chars() {
+        match ch {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            '(' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn parse_items(
+    input: &str,
+    pos: &mut usize,
+    variants: &[&str],
+    openers: &[usize],
+    nested: bool,
+    separator: &str,
+    out: &mut Vec<(Range<usize>, String, Vec<usize>)>,
+) -> Option<()> {
+    let bytes = input.as_bytes();
+    loop {
+        while *pos < bytes.len() && bytes[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos == bytes.len() {
+            return (!nested).then_some(());
+        }
+        if bytes[*pos] == b')' {
+            *pos += 1;
+            return nested.then_some(());
+        }
+
+        let start = *pos;
+        let mut depth = 0usize;
+        while *pos < bytes.len() {
+            match bytes[*pos] {
+                b'[' => depth += 1,
+                b']' => depth = depth.saturating_sub(1),
+                b'(' | b')' if depth == 0 => break,
+                byte if depth == 0 && byte.is_ascii_whitespace() => break,
+                _ => {}
+            }
+            *pos += 1;
+        }
+        let word = &input[start..*pos];
+        if word.is_empty() {
+            return None;
+        }
+
+        if bytes.get(*pos) == Some(&b'(') {
+            *pos += 1;
+            let mut nested_variants = variants.to_vec();
+            nested_variants.push(word);
+            let mut nested_openers = openers.to_vec();
+            nested_openers.push(start);
+            parse_items(
+                input,
+                pos,
+                &nested_variants,
+                &nested_openers,
+                true,
+                separator,
+                out,
+            )?;
+        } else {
+            let mut parts = variants.to_vec();
+            parts.push(word);
+            out.push((start..*pos, parts.join(separator), openers.to_vec()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_groups_together_when_splitting() {
+        assert_eq!(
+            split_class_list("p-4 lg(px-2 p-10) md(hover(a b)) mt-2"),
+            vec!["p-4", "lg(px-2 p-10)", "md(hover(a b))", "mt-2"]
+        );
+        assert_eq!(split_class_list("lg(px-2 p-10"), vec!["lg(px-2", "p-10"]);
+    }
+
+    #[test]
+    fn ignores_parentheses_in_arbitrary_values_when_splitting() {
+        assert_eq!(
+            split_class_list("content-['('] bg-[url(a)] lg(px-2 w-[calc(100%_-_1rem)]) mt-2"),
+            vec![
+                "content-['(']",
+                "bg-[url(a)]",
+                "lg(px-2 w-[calc(100%_-_1rem)])",
+                "mt-2"
+            ]
+        );
+        assert_eq!(
+            split_class_list("content-[')'] p-4"),
+            vec!["content-[')']", "p-4"]
+        );
+    }
+
+    fn expanded(items: &[(&[&str], &str)]) -> Option<Vec<(Vec<String>, String)>> {
+        Some(
+            items
+                .iter()
+                .map(|(tokens, utility)| {
+                    (
+                        tokens.iter().map(|token| token.to_string()).collect(),
+                        utility.to_string(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn expands_groups_to_literal_tokens() {
+        assert_eq!(
+            expand_group("lg(px-2 p-10)", ":"),
+            expanded(&[
+                (&["lg(px-2"], "lg:px-2"),
+                (&["lg(px-2", "p-10)"], "lg:p-10"),
+            ])
+        );
+        assert_eq!(
+            expand_group("md(hover(bg-red-500 text-white) p-2)", ":"),
+            expanded(&[
+                (&["md(hover(bg-red-500"], "md:hover:bg-red-500"),
+                (
+                    &["md(hover(bg-red-500", "text-white)"],
+                    "md:hover:text-white"
+                ),
+                (&["md(hover(bg-red-500", "p-2)"], "md:p-2"),
+            ])
+        );
+        assert_eq!(
+            expand_group("md(p-1 hover(a b))", ":"),
+            expanded(&[
+                (&["md(p-1"], "md:p-1"),
+                (&["md(p-1", "hover(a"], "md:hover:a"),
+                (&["md(p-1", "hover(a", "b))"], "md:hover:b"),
+            ])
+        );
+        assert_eq!(
+            expand_group("lg(w-[calc(100%_-_1rem)])", ":"),
+            expanded(&[(&["lg(w-[calc(100%_-_1rem)])"], "lg:w-[calc(100%_-_1rem)]")])
+        );
+    }
+
+    #[test]
+    fn keeps_shared_closing_tokens_apart() {
+        let small = expand_group("sm(m-2 p-10)", ":").unwrap();
+        let large = expand_group("lg(px-2 p-10)", ":").unwrap();
+        assert_eq!(small[1].0, vec!["sm(m-2", "p-10)"]);
+        assert_eq!(large[1].0, vec!["lg(px-2", "p-10)"]);
+    }
+
+    #[test]
+    fn rejects_non_groups() {
+        assert_eq!(expand_group("bg-[url(a.png)]", ":"), None);
+        assert_eq!(expand_group("lg(px-2", ":"), None);
+        assert_eq!(expand_group("lg()", ":"), Some(vec![]));
+    }
+}
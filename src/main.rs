@@ -2,7 +2,10 @@ mod cache;
 mod data_manager;
 mod engine;
 mod generator;
+mod grouping;
 mod parser;
+#[cfg(test)]
+mod properties;
 mod utils;
 mod watcher;
 
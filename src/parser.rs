@@ -1,3 +1,4 @@
+use crate::grouping;
 use oxc_allocator::Allocator;
 use oxc_ast::ast::{
     self, ExportDefaultDeclarationKind, JSXAttributeItem, JSXOpeningElement, Program,
@@ -167,9 +168,8 @@ impl ClassNameVisitor {
                 if let ast::JSXAttributeName::Identifier(ident) = &attr.name {
                     if ident.name == "className" {
                         if let Some(ast::JSXAttributeValue::StringLiteral(lit)) = &attr.value {
-                            lit.value.split_whitespace().for_each(|cn| {
-                                self.class_names.insert(cn.to_string());
-                            });
+                            self.class_names
+                                .extend(grouping::split_class_list(&lit.value));
                         }
                     }
                 }
@@ -0,0 +1,104 @@
+//! Parsing of the property part of `prefix|...` keys in styles.toml. build.rs includes
+//! this file to compile the keys, and the crate builds it for its tests.
+
+/// Splits the property part of a `prefix|...` key into `(name, template)` pairs.
+///
+/// Entries are separated by top-level commas; an entry may carry a value template
+/// after the first `:`, e.g. `margin-left: calc({value} * -1)`. Once an entry has a
+/// template, following comma-separated parts belong to it until one starts with `name:`.
+pub fn parse_properties(spec: &str) -> Vec<(String, Option<String>)> {
+    let mut segments = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, ch) in spec.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                segments.push(&spec[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    segments.push(&spec[start..]);
+
+    let mut properties: Vec<(String, Option<String>)> = Vec::new();
+    for segment in segments {
+        let declaration = segment
+            .split_once(':')
+            .filter(|(name, _)| is_property_name(name.trim()));
+        match (declaration, properties.last_mut()) {
+            (Some((name, template)), _) => {
+                properties.push((name.trim().to_string(), Some(template.trim().to_string())))
+            }
+            (None, Some((_, Some(template)))) => {
+                template.push(',');
+                template.push_str(segment);
+            }
+            (None, _) if !segment.trim().is_empty() => {
+                properties.push((segment.trim().to_string(), None))
+            }
+            (None, _) => {}
+        }
+    }
+    properties
+}
+
+fn is_property_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn property(name: &str, template: Option<&str>) -> (String, Option<String>) {
+        (name.to_string(), template.map(str::to_string))
+    }
+
+    #[test]
+    fn splits_plain_properties() {
+        assert_eq!(parse_properties("width"), [property("width", None)]);
+        assert_eq!(
+            parse_properties("padding-left, padding-right"),
+            [
+                property("padding-left", None),
+                property("padding-right", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_templates_with_commas_together() {
+        assert_eq!(
+            parse_properties("margin-right, margin-left: calc({value} * -1)"),
+            [
+                property("margin-right", None),
+                property("margin-left", Some("calc({value} * -1)")),
+            ]
+        );
+        assert_eq!(
+            parse_properties(
+                "--tw-gradient-via: {value}, --tw-gradient-via-stops: var(--a) var(--b), var(--c)"
+            ),
+            [
+                property("--tw-gradient-via", Some("{value}")),
+                property(
+                    "--tw-gradient-via-stops",
+                    Some("var(--a) var(--b), var(--c)")
+                ),
+            ]
+        );
+        assert_eq!(
+            parse_properties("--filter-drop-shadow: drop-shadow(0 0 {value} rgb(0 0 0 / 0.15))"),
+            [property(
+                "--filter-drop-shadow",
+                Some("drop-shadow(0 0 {value} rgb(0 0 0 / 0.15))")
+            )]
+        );
+    }
+}